@@ -0,0 +1,291 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Deploys `erc721` collections from a registered code hash and keeps a registry
+/// of the collections of each creator.
+#[ink::contract]
+mod factory {
+    use erc721::Erc721Ref;
+    use ink::prelude::string::String;
+    use ink::scale::Encode;
+    use ink::storage::Mapping;
+
+    #[ink(storage)]
+    pub struct Factory {
+        /// The account allowed to change the collection code hash.
+        owner: AccountId,
+        /// The code hash collections are instantiated from.
+        collection_code_hash: Hash,
+        /// Mapping from collection to its registry entry.
+        collections: Mapping<AccountId, Collection>,
+        /// Mapping from creator and index to the collections of the creator.
+        creator_collections: Mapping<(AccountId, u32), AccountId>,
+        /// Mapping from creator to the number of collections created.
+        creator_collections_count: Mapping<AccountId, u32>,
+    }
+
+    /// A collection deployed by the factory.
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Collection {
+        /// The account that created the collection.
+        pub creator: AccountId,
+        /// The name of the collection.
+        pub name: String,
+        /// The symbol of the collection.
+        pub symbol: String,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Error {
+        NotOwner,
+        InvalidAdmin,
+        InstantiationFailed,
+    }
+
+    /// Event emitted when a collection is created.
+    #[ink(event)]
+    pub struct CollectionCreated {
+        #[ink(topic)]
+        collection: AccountId,
+        #[ink(topic)]
+        creator: AccountId,
+        #[ink(topic)]
+        admin: AccountId,
+        name: String,
+        symbol: String,
+        max_supply: Option<u32>,
+    }
+
+    /// Event emitted when the collection code hash changes.
+    #[ink(event)]
+    pub struct CollectionCodeHashUpdated {
+        code_hash: Hash,
+    }
+
+    impl Factory {
+        /// Creates a new factory instantiating collections from `code_hash`.
+        ///
+        /// The caller becomes the owner of the factory.
+        #[ink(constructor)]
+        pub fn new(code_hash: Hash) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                collection_code_hash: code_hash,
+                collections: Mapping::new(),
+                creator_collections: Mapping::new(),
+                creator_collections_count: Mapping::new(),
+            }
+        }
+
+        /// Returns the owner of the factory.
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// Returns the code hash collections are instantiated from.
+        #[ink(message)]
+        pub fn collection_code_hash(&self) -> Hash {
+            self.collection_code_hash
+        }
+
+        /// Sets the code hash new collections are instantiated from. Only the
+        /// owner can change it.
+        #[ink(message)]
+        pub fn set_collection_code_hash(&mut self, code_hash: Hash) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+            self.collection_code_hash = code_hash;
+            self.env().emit_event(CollectionCodeHashUpdated { code_hash });
+            Ok(())
+        }
+
+        /// Returns the registry entry of the collection, if deployed by the factory.
+        #[ink(message)]
+        pub fn collection(&self, collection: AccountId) -> Option<Collection> {
+            self.collections.get(collection)
+        }
+
+        /// Returns the number of collections created by `creator`.
+        #[ink(message)]
+        pub fn collection_count(&self, creator: AccountId) -> u32 {
+            self.creator_collections_count.get(creator).unwrap_or(0)
+        }
+
+        /// Returns the collection at the given index of the collections created
+        /// by `creator`.
+        #[ink(message)]
+        pub fn collection_of_creator_by_index(
+            &self,
+            creator: AccountId,
+            index: u32,
+        ) -> Option<AccountId> {
+            self.creator_collections.get((creator, index))
+        }
+
+        /// Creates a new collection administered by `admin`, optionally capped at
+        /// `max_supply` tokens, and returns its address.
+        ///
+        /// The admin must not be the zero account, or nobody could manage the
+        /// collection.
+        #[ink(message)]
+        pub fn create_collection(
+            &mut self,
+            name: String,
+            symbol: String,
+            base_uri: String,
+            contract_uri: String,
+            admin: AccountId,
+            max_supply: Option<u32>,
+        ) -> Result<AccountId, Error> {
+            if admin == AccountId::from([0x0; 32]) {
+                return Err(Error::InvalidAdmin);
+            }
+            let creator = self.env().caller();
+            let index = self.collection_count(creator);
+            let collection = Erc721Ref::with_config(
+                name.clone(),
+                symbol.clone(),
+                base_uri,
+                contract_uri,
+                admin,
+                max_supply,
+            )
+            .code_hash(self.collection_code_hash)
+            .endowment(0)
+            .salt_bytes((creator, index).encode())
+            .try_instantiate()
+            .map_err(|_| Error::InstantiationFailed)?
+            .map_err(|_| Error::InstantiationFailed)?;
+            let collection = ink::ToAccountId::<Environment>::to_account_id(&collection);
+            self.register(collection, creator, admin, name, symbol, max_supply);
+            Ok(collection)
+        }
+
+        /// Records `collection` as the next collection of `creator`.
+        fn register(
+            &mut self,
+            collection: AccountId,
+            creator: AccountId,
+            admin: AccountId,
+            name: String,
+            symbol: String,
+            max_supply: Option<u32>,
+        ) {
+            let index = self.collection_count(creator);
+            self.collections.insert(
+                collection,
+                &Collection {
+                    creator,
+                    name: name.clone(),
+                    symbol: symbol.clone(),
+                },
+            );
+            self.creator_collections.insert((creator, index), &collection);
+            self.creator_collections_count
+                .insert(creator, &index.checked_add(1).unwrap());
+            self.env().emit_event(CollectionCreated {
+                collection,
+                creator,
+                admin,
+                name,
+                symbol,
+                max_supply,
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn set_collection_code_hash_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Alice creates a factory and becomes its owner.
+            let mut factory = Factory::new(Hash::from([0x1; 32]));
+            assert_eq!(factory.owner(), accounts.alice);
+            // Bob cannot change the code hash.
+            set_caller(accounts.bob);
+            assert_eq!(
+                factory.set_collection_code_hash(Hash::from([0x2; 32])),
+                Err(Error::NotOwner)
+            );
+            // Alice changes the code hash.
+            set_caller(accounts.alice);
+            assert_eq!(
+                factory.set_collection_code_hash(Hash::from([0x2; 32])),
+                Ok(())
+            );
+            assert_eq!(factory.collection_code_hash(), Hash::from([0x2; 32]));
+            // No collections have been created yet.
+            assert_eq!(factory.collection_count(accounts.alice), 0);
+            assert_eq!(
+                factory.collection_of_creator_by_index(accounts.alice, 0),
+                None
+            );
+        }
+
+        #[ink::test]
+        fn create_collection_fails() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut factory = Factory::new(Hash::from([0x1; 32]));
+            // A collection cannot be administered by the zero account.
+            set_caller(accounts.bob);
+            assert_eq!(
+                factory.create_collection(
+                    String::from("Sirona"),
+                    String::from("SIR"),
+                    String::new(),
+                    String::new(),
+                    AccountId::from([0x0; 32]),
+                    None,
+                ),
+                Err(Error::InvalidAdmin)
+            );
+            assert_eq!(factory.collection_count(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn register_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut factory = Factory::new(Hash::from([0x1; 32]));
+            // The off-chain environment cannot instantiate collections, so Bob's
+            // collections are registered directly.
+            for collection in [accounts.eve, accounts.frank] {
+                factory.register(
+                    collection,
+                    accounts.bob,
+                    accounts.charlie,
+                    String::from("Sirona"),
+                    String::from("SIR"),
+                    Some(10),
+                );
+            }
+            assert_eq!(factory.collection_count(accounts.bob), 2);
+            assert_eq!(
+                factory.collection_of_creator_by_index(accounts.bob, 1),
+                Some(accounts.frank)
+            );
+            assert_eq!(
+                factory.collection(accounts.eve),
+                Some(Collection {
+                    creator: accounts.bob,
+                    name: String::from("Sirona"),
+                    symbol: String::from("SIR"),
+                })
+            );
+            assert_eq!(2, ink::env::test::recorded_events().count());
+        }
+
+        fn set_caller(sender: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(sender);
+        }
+    }
+}
@@ -1,14 +1,42 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
+pub use self::erc721::{Erc721, Erc721Ref};
+
 #[ink::contract]
 mod erc721 {
-    use ink::storage::Mapping;
-    use ink::prelude::string::String;
+    use ink::codegen::TraitCallBuilder;
+    use ink::env::hash::{Blake2x256, Keccak256};
+    use ink::scale::Encode;
+    use ink::storage::{Lazy, Mapping};
+    use ink::prelude::string::{String, ToString};
+    use ink::prelude::vec::Vec;
+    use erc721_traits::{
+        psp34, Erc721Approvals, Erc721Core, Erc721Metadata, Erc721Receiver, Error, Id,
+        PSP34Error, TokenId, TokenURI,
+    };
+    use shares::{PSP22Error, SharesRef, PSP22};
+
+    /// An Ethereum address.
+    pub type EthAddress = [u8; 20];
+    /// An offer ID.
+    pub type OfferId = u64;
+    /// A swap ID.
+    pub type SwapId = u64;
 
-    /// A token ID.
-    pub type TokenId = u32;
-    /// The URI, where the asset is stored.
-    pub type TokenURI = String;
+    /// The maximum number of entries returned by a bulk query.
+    pub const MAX_QUERY_SIZE: u32 = 100;
+    /// The basis points denominator used for royalties.
+    pub const BASIS_POINTS: u16 = 10_000;
+    /// The storage layout version of this code.
+    ///
+    /// The packed root of `Erc721` keeps its version 1 layout, so that it still
+    /// decodes after an upgrade and `Erc721::migrate` can run. State added since
+    /// lives in `Mapping` and `Lazy` fields under storage keys of their own. Must
+    /// be bumped, together with a matching step in `Erc721::migrate`, whenever
+    /// stored values have to be rewritten.
+    pub const STORAGE_VERSION: u32 = 1;
+    /// The time in milliseconds by which a late bid extends an English auction.
+    pub const AUCTION_EXTENSION: Timestamp = 5 * 60 * 1000;
 
     #[ink(storage)]
     #[derive(Default)]
@@ -23,18 +51,305 @@ mod erc721 {
         operator_approvals: Mapping<(AccountId, AccountId), ()>,
         /// Mapping to store token URIs.
         token_uris: Mapping<TokenId, TokenURI>,
+        /// Mapping from role and account to role membership.
+        roles: Mapping<(Role, AccountId), ()>,
+        /// Mapping from index to token of all existing tokens.
+        all_tokens: Mapping<u32, TokenId>,
+        /// Mapping from token to its position in `all_tokens`.
+        all_tokens_index: Mapping<TokenId, u32>,
+        /// Number of existing tokens.
+        all_tokens_count: u32,
+        /// Mapping from owner and index to owned token.
+        owned_tokens: Mapping<(AccountId, u32), TokenId>,
+        /// Mapping from token to its position in `owned_tokens`.
+        owned_tokens_index: Mapping<TokenId, u32>,
+        /// Royalty applied to tokens without a royalty of their own.
+        default_royalty: Option<RoyaltyInfo>,
+        /// Mapping from token to its royalty.
+        token_royalties: Mapping<TokenId, RoyaltyInfo>,
+        /// Set of paused operations.
+        paused: Mapping<Operation, ()>,
+        /// The storage layout version the storage was last migrated to.
+        storage_version: u32,
+        // Fields below are stored under keys of their own, outside the packed root.
+        /// Whether newly minted tokens are soulbound.
+        soulbound_by_default: Lazy<bool>,
+        /// Burn authorization of tokens without one of their own.
+        default_burn_auth: Lazy<BurnAuth>,
+        /// Set of soulbound tokens.
+        soulbound_tokens: Mapping<TokenId, ()>,
+        /// Mapping from token to its burn authorization.
+        token_burn_auths: Mapping<TokenId, BurnAuth>,
+        /// The last token ID assigned by `mint_next`.
+        last_token_id: Lazy<TokenId>,
+        /// Maximum number of tokens that can ever be minted, if capped.
+        max_supply: Lazy<Option<u32>>,
+        /// Number of tokens minted so far.
+        total_minted: Lazy<u32>,
+        /// Number of tokens burned so far.
+        total_burned: Lazy<u32>,
+        /// Maximum number of tokens minted to a single account, if limited.
+        mint_limit: Lazy<Option<u32>>,
+        /// Mapping from account to number of tokens minted to it.
+        minted_count: Mapping<AccountId, u32>,
+        /// Time from which minting is open, if restricted.
+        mint_start: Lazy<Option<Timestamp>>,
+        /// Time from which minting is closed, if restricted.
+        mint_end: Lazy<Option<Timestamp>>,
+        /// The sr25519 public key signing mint vouchers, if any.
+        voucher_signer: Lazy<Option<[u8; 32]>>,
+        /// Set of redeemed mint voucher nonces.
+        used_voucher_nonces: Mapping<u64, ()>,
+        /// Mapping from token to the nonce expected by its next permit.
+        permit_nonces: Mapping<TokenId, u64>,
+        /// Mapping from role and Ethereum address to role membership.
+        eth_roles: Mapping<(Role, EthAddress), ()>,
+        /// Mapping from signer to the nonce expected by its next signed operation.
+        signed_operation_nonces: Mapping<AccountId, u64>,
+        /// Mapping from signer to the balance deposited to pay relayer fees.
+        fee_deposits: Mapping<AccountId, Balance>,
+        /// Mapping from token to its fixed-price listing.
+        listings: Mapping<TokenId, Listing>,
+        /// The account receiving the marketplace fee, if charged.
+        marketplace_fee_receiver: Lazy<Option<AccountId>>,
+        /// The marketplace fee in basis points of the sale price.
+        marketplace_fee_basis_points: Lazy<u16>,
+        /// Mapping from escrowed token to its running auction.
+        auctions: Mapping<TokenId, Auction>,
+        /// Mapping from offer ID to an open offer.
+        offers: Mapping<OfferId, Offer>,
+        /// The ID of the last offer made.
+        last_offer_id: Lazy<OfferId>,
+        /// Mapping from swap ID to a pending swap.
+        swaps: Mapping<SwapId, Swap>,
+        /// The ID of the last swap proposed.
+        last_swap_id: Lazy<SwapId>,
+        /// Mapping from token to its user and the time the user role expires.
+        token_users: Mapping<TokenId, UserInfo>,
+        /// The code hash of the share contract instantiated for vaulted tokens.
+        share_code_hash: Lazy<Option<Hash>>,
+        /// Mapping from vaulted token to its share contract.
+        vaults: Mapping<TokenId, AccountId>,
+        /// The number of share contracts instantiated, used as salt.
+        vaults_created: Lazy<u64>,
+        /// The name of the collection.
+        name: Lazy<String>,
+        /// The symbol of the collection.
+        symbol: Lazy<String>,
+        /// The URI prefix of tokens without a URI of their own.
+        base_uri: Lazy<String>,
+        /// The URI of the collection-level metadata.
+        contract_uri: Lazy<String>,
+        /// The accounts allowed to change token URIs.
+        metadata_auth: Lazy<MetadataAuth>,
+        /// Whether the metadata of the whole collection is frozen.
+        metadata_frozen: Lazy<bool>,
+        /// Mapping from token to whether its metadata is frozen.
+        frozen_tokens: Mapping<TokenId, ()>,
+    }
+
+    /// A role granting access to privileged operations.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Role {
+        /// Can grant and revoke roles.
+        Admin,
+        /// Can mint new tokens.
+        Minter,
+        /// Can burn tokens of any owner.
+        Burner,
+        /// Can manage token metadata.
+        MetadataManager,
+    }
+
+    /// An operation that can be paused.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Operation {
+        /// Every operation below.
+        All,
+        /// Minting new tokens.
+        Mint,
+        /// Transferring tokens.
+        Transfer,
+        /// Approving accounts and operators.
+        Approve,
+        /// Burning tokens.
+        Burn,
+    }
+
+    /// The accounts allowed to burn a token, as defined by ERC-5484.
+    #[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum BurnAuth {
+        /// Only a burner can burn the token.
+        IssuerOnly,
+        /// Only the owner can burn the token.
+        OwnerOnly,
+        /// Both the owner and a burner can burn the token.
+        #[default]
+        Both,
+        /// The token cannot be burned.
+        Neither,
+    }
+
+    /// The accounts allowed to change the URI of a token.
+    #[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum MetadataAuth {
+        /// Only a metadata manager can change the URI.
+        #[default]
+        ManagerOnly,
+        /// Only the owner can change the URI.
+        OwnerOnly,
+        /// Both the owner and a metadata manager can change the URI.
+        Both,
+    }
+
+    /// An off-chain authorization to mint a token, signed by the voucher signer.
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct MintVoucher {
+        /// The account receiving the token.
+        pub recipient: AccountId,
+        /// The ID of the token.
+        pub id: TokenId,
+        /// The URI of the token.
+        pub uri: TokenURI,
+        /// Last time at which the voucher can be redeemed.
+        pub expiry: Timestamp,
+        /// Unique number preventing the voucher from being redeemed twice.
+        pub nonce: u64,
+    }
+
+    /// An operation relayed through `execute_signed` on behalf of its signer.
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum SignedOperation {
+        /// Transfers token `id` `from` an account `to` another.
+        Transfer {
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+        },
+        /// Approves `to` to transfer token `id`.
+        Approve { to: AccountId, id: TokenId },
+        /// Approves or disapproves `operator` for all tokens.
+        SetApprovalForAll { operator: AccountId, approved: bool },
+        /// Burns token `id`.
+        Burn { id: TokenId },
+    }
+
+    /// A token offered for sale at a fixed price.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Listing {
+        /// The owner who listed the token.
+        pub seller: AccountId,
+        /// The price the token is sold for.
+        pub price: Balance,
+    }
+
+    /// The pricing rules of an auction.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum AuctionKind {
+        /// Ascending bids, won by the highest bid of at least the reserve price.
+        English { reserve_price: Balance },
+        /// A price falling linearly from the start to the end price, won by the
+        /// first buyer.
+        Dutch {
+            start_price: Balance,
+            end_price: Balance,
+        },
+    }
+
+    /// A bid placed in an English auction.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Bid {
+        /// The account that placed the bid.
+        pub bidder: AccountId,
+        /// The escrowed amount of the bid.
+        pub amount: Balance,
+    }
+
+    /// An auction of a token escrowed by the contract.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Auction {
+        /// The owner who put the token up for auction.
+        pub seller: AccountId,
+        /// The pricing rules of the auction.
+        pub kind: AuctionKind,
+        /// The time the auction started.
+        pub start: Timestamp,
+        /// The time the auction ends.
+        pub end: Timestamp,
+        /// The highest bid of an English auction.
+        pub highest_bid: Option<Bid>,
+    }
+
+    /// An offer to buy a token for escrowed funds.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Offer {
+        /// The account that made the offer.
+        pub buyer: AccountId,
+        /// The token asked for, or `None` for any token of the collection.
+        pub id: Option<TokenId>,
+        /// The escrowed amount offered.
+        pub amount: Balance,
+        /// The time the offer expires.
+        pub expiry: Timestamp,
+    }
+
+    /// A proposed exchange of tokens and native value between two parties.
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Swap {
+        /// The party that proposed the swap.
+        pub proposer: AccountId,
+        /// The party that can accept the swap.
+        pub counterparty: AccountId,
+        /// The tokens of the proposer given to the counterparty.
+        pub offered: Vec<TokenId>,
+        /// The tokens of the counterparty given to the proposer.
+        pub requested: Vec<TokenId>,
+        /// The escrowed value given to the counterparty.
+        pub offered_value: Balance,
+        /// The value the counterparty pays to the proposer.
+        pub requested_value: Balance,
     }
 
+    /// The user of a token, who may use it without owning it.
     #[derive(Debug, PartialEq, Eq, Copy, Clone)]
     #[ink::scale_derive(Encode, Decode, TypeInfo)]
-    pub enum Error {
-        NotOwner,
-        NotApproved,
-        TokenExists,
-        TokenNotFound,
-        CannotInsert,
-        CannotFetchValue,
-        NotAllowed,
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct UserInfo {
+        /// The account granted the user role.
+        pub user: AccountId,
+        /// The time after which the user role expires.
+        pub expires: Timestamp,
+    }
+
+    /// A royalty paid on secondary sales.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct RoyaltyInfo {
+        /// The account receiving the royalty.
+        pub receiver: AccountId,
+        /// The share of the sale price in basis points.
+        pub basis_points: u16,
     }
 
     /// Event emitted when a token transfer occurs.
@@ -70,603 +385,5125 @@ mod erc721 {
         approved: bool,
     }
 
-    /// Event emitted when a new NFT is minted.
+    /// Event emitted when a role is granted to an account.
     #[ink(event)]
-    pub struct Mint {
+    pub struct RoleGranted {
         #[ink(topic)]
-        to: AccountId,
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        sender: AccountId,
+    }
+
+    /// Event emitted when a role is revoked from an account.
+    #[ink(event)]
+    pub struct RoleRevoked {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        sender: AccountId,
+    }
+
+    /// Event emitted when the default royalty or the royalty of a token changes.
+    ///
+    /// `id` is `None` for the default royalty and `royalty` is `None` once removed.
+    #[ink(event)]
+    pub struct RoyaltyUpdated {
+        #[ink(topic)]
+        id: Option<TokenId>,
+        royalty: Option<RoyaltyInfo>,
+    }
+
+    /// Event emitted when an operation is paused.
+    #[ink(event)]
+    pub struct Paused {
+        #[ink(topic)]
+        operation: Operation,
+        #[ink(topic)]
+        account: AccountId,
+    }
+
+    /// Event emitted when an operation is unpaused.
+    #[ink(event)]
+    pub struct Unpaused {
+        #[ink(topic)]
+        operation: Operation,
+        #[ink(topic)]
+        account: AccountId,
+    }
+
+    /// Event emitted when the contract code is upgraded.
+    #[ink(event)]
+    pub struct Upgraded {
+        #[ink(topic)]
+        code_hash: Hash,
+    }
+
+    /// Event emitted when the storage is migrated to a newer layout version.
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    /// Event emitted when a token becomes soulbound.
+    #[ink(event)]
+    pub struct Locked {
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    /// Event emitted when a role is granted to an Ethereum address.
+    #[ink(event)]
+    pub struct EthRoleGranted {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        address: EthAddress,
+        #[ink(topic)]
+        sender: AccountId,
+    }
+
+    /// Event emitted when a role is revoked from an Ethereum address.
+    #[ink(event)]
+    pub struct EthRoleRevoked {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        address: EthAddress,
+        #[ink(topic)]
+        sender: AccountId,
+    }
+
+    /// Event emitted when a relayer executes a signed operation.
+    #[ink(event)]
+    pub struct SignedOperationExecuted {
+        #[ink(topic)]
+        signer: AccountId,
+        #[ink(topic)]
+        relayer: AccountId,
+        nonce: u64,
+        fee: Balance,
+    }
+
+    /// Event emitted when a token is listed for sale.
+    #[ink(event)]
+    pub struct Listed {
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        seller: AccountId,
+        price: Balance,
+    }
+
+    /// Event emitted when a listed token is sold.
+    #[ink(event)]
+    pub struct Sold {
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        seller: AccountId,
+        #[ink(topic)]
+        buyer: AccountId,
+        price: Balance,
+    }
+
+    /// Event emitted when a listing is cancelled or invalidated.
+    #[ink(event)]
+    pub struct Delisted {
+        #[ink(topic)]
+        id: TokenId,
+    }
+
+    /// Event emitted when a token is put up for auction.
+    #[ink(event)]
+    pub struct AuctionStarted {
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        seller: AccountId,
+        kind: AuctionKind,
+        end: Timestamp,
+    }
+
+    /// Event emitted when a bid is placed in an English auction.
+    #[ink(event)]
+    pub struct BidPlaced {
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        bidder: AccountId,
+        amount: Balance,
+        end: Timestamp,
+    }
+
+    /// Event emitted when an auction is settled. `winner` is `None` when the token
+    /// went back to the seller.
+    #[ink(event)]
+    pub struct AuctionSettled {
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        winner: Option<AccountId>,
+        price: Balance,
+    }
+
+    /// Event emitted when an offer is made. `id` is `None` for collection offers.
+    #[ink(event)]
+    pub struct OfferMade {
+        #[ink(topic)]
+        offer_id: OfferId,
+        #[ink(topic)]
+        buyer: AccountId,
+        #[ink(topic)]
+        id: Option<TokenId>,
+        amount: Balance,
+        expiry: Timestamp,
+    }
+
+    /// Event emitted when an offer is cancelled and its funds are refunded.
+    #[ink(event)]
+    pub struct OfferCancelled {
+        #[ink(topic)]
+        offer_id: OfferId,
+    }
+
+    /// Event emitted when an offer is accepted.
+    #[ink(event)]
+    pub struct OfferAccepted {
+        #[ink(topic)]
+        offer_id: OfferId,
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        seller: AccountId,
+        buyer: AccountId,
+        price: Balance,
+    }
+
+    /// Event emitted when a swap is proposed.
+    #[ink(event)]
+    pub struct SwapProposed {
+        #[ink(topic)]
+        swap_id: SwapId,
+        #[ink(topic)]
+        proposer: AccountId,
+        #[ink(topic)]
+        counterparty: AccountId,
+        offered: Vec<TokenId>,
+        requested: Vec<TokenId>,
+        offered_value: Balance,
+        requested_value: Balance,
+    }
+
+    /// Event emitted when a swap is executed.
+    #[ink(event)]
+    pub struct SwapExecuted {
+        #[ink(topic)]
+        swap_id: SwapId,
+    }
+
+    /// Event emitted when a swap is cancelled or rejected.
+    #[ink(event)]
+    pub struct SwapCancelled {
+        #[ink(topic)]
+        swap_id: SwapId,
+    }
+
+    /// Event emitted when the user of a token changes. `user` is `None` when the
+    /// user role is cleared.
+    #[ink(event)]
+    pub struct UpdateUser {
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        user: Option<AccountId>,
+        expires: Timestamp,
+    }
+
+    /// Event emitted when a token is locked in the vault and split into shares.
+    #[ink(event)]
+    pub struct Fractionalized {
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        shares: AccountId,
+        #[ink(topic)]
+        holder: AccountId,
+        supply: Balance,
+    }
+
+    /// Event emitted when a vaulted token is redeemed by burning all its shares.
+    #[ink(event)]
+    pub struct Redeemed {
+        #[ink(topic)]
+        id: TokenId,
+        #[ink(topic)]
+        holder: AccountId,
+    }
+
+    /// Event emitted when the base URI changes.
+    #[ink(event)]
+    pub struct BaseURIUpdated {
+        base_uri: String,
+    }
+
+    /// Event emitted when the contract URI changes.
+    #[ink(event)]
+    pub struct ContractURIUpdated {
+        contract_uri: String,
+    }
+
+    /// Event emitted when the metadata of a token changes, as defined by ERC-4906.
+    #[ink(event)]
+    pub struct MetadataUpdate {
         #[ink(topic)]
         id: TokenId,
-        uri: TokenURI,
+    }
+
+    /// Event emitted when the metadata of a range of tokens changes, as defined
+    /// by ERC-4906.
+    #[ink(event)]
+    pub struct BatchMetadataUpdate {
+        from_id: TokenId,
+        to_id: TokenId,
+    }
+
+    /// Event emitted when metadata is frozen for good. `id` is `None` when the
+    /// whole collection is frozen, with `value` holding the base URI.
+    #[ink(event)]
+    pub struct PermanentURI {
+        value: String,
+        #[ink(topic)]
+        id: Option<TokenId>,
     }
 
     impl Erc721 {
         /// Creates a new ERC-721 token contract.
+        ///
+        /// The caller is granted all roles.
         #[ink(constructor)]
         pub fn new() -> Self {
-            Default::default()
+            Self::with_caller_as_admin(None)
         }
 
-        /// Returns the balance of the owner.
+        /// Creates a new ERC-721 token contract capped at `max_supply` tokens.
         ///
-        /// This represents the amount of unique tokens the owner has.
-        #[ink(message)]
-        pub fn balance_of(&self, owner: AccountId) -> u32 {
-            self.balance_of_or_zero(&owner)
+        /// The caller is granted all roles.
+        #[ink(constructor)]
+        pub fn with_max_supply(max_supply: u32) -> Self {
+            Self::with_caller_as_admin(Some(max_supply))
         }
 
-        /// Returns the owner of the token.
-        #[ink(message)]
-        pub fn owner_of(&self, id: TokenId) -> Option<AccountId> {
-            self.token_owner.get(id)
+        /// Creates a new ERC-721 token contract for a named collection, optionally
+        /// capped at `max_supply` tokens.
+        ///
+        /// Tokens minted without a URI resolve to `base_uri` followed by their ID.
+        /// The `admin` is granted all roles.
+        #[ink(constructor)]
+        pub fn with_config(
+            name: String,
+            symbol: String,
+            base_uri: String,
+            contract_uri: String,
+            admin: AccountId,
+            max_supply: Option<u32>,
+        ) -> Self {
+            let mut instance = Self::default();
+            instance.name.set(&name);
+            instance.symbol.set(&symbol);
+            instance.base_uri.set(&base_uri);
+            instance.contract_uri.set(&contract_uri);
+            for role in [
+                Role::Admin,
+                Role::Minter,
+                Role::Burner,
+                Role::MetadataManager,
+            ] {
+                instance.roles.insert((role, admin), &());
+                Self::env().emit_event(RoleGranted {
+                    role,
+                    account: admin,
+                    sender: Self::env().caller(),
+                });
+            }
+            instance.storage_version = STORAGE_VERSION;
+            instance.max_supply.set(&max_supply);
+            instance
+        }
+
+        /// Returns an unnamed collection administered by the caller.
+        fn with_caller_as_admin(max_supply: Option<u32>) -> Self {
+            Self::with_config(
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                Self::env().caller(),
+                max_supply,
+            )
         }
 
-        /// Returns the approved account ID for this token if any.
+        /// Returns the URI of the collection-level metadata.
         #[ink(message)]
-        pub fn get_approved(&self, id: TokenId) -> Option<AccountId> {
-            self.token_approvals.get(id)
+        pub fn contract_uri(&self) -> String {
+            self.contract_uri.get_or_default()
         }
 
-        /// Returns `true` if the operator is approved by the owner.
+        /// Returns the URI prefix of tokens without a URI of their own.
         #[ink(message)]
-        pub fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
-            self.approved_for_all(owner, operator)
+        pub fn base_uri(&self) -> String {
+            self.base_uri.get_or_default()
         }
 
-        /// Approves or disapproves the operator for all tokens of the caller.
+        /// Sets the URI prefix of tokens without a URI of their own. Only an admin
+        /// can change it.
         #[ink(message)]
-        pub fn set_approval_for_all(
-            &mut self,
-            to: AccountId,
-            approved: bool,
-        ) -> Result<(), Error> {
-            self.approve_for_all(to, approved)?;
+        pub fn set_base_uri(&mut self, base_uri: String) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.ensure_metadata_not_frozen()?;
+            self.base_uri.set(&base_uri);
+            self.env().emit_event(BaseURIUpdated { base_uri });
+            self.env().emit_event(BatchMetadataUpdate {
+                from_id: TokenId::MIN,
+                to_id: TokenId::MAX,
+            });
             Ok(())
         }
 
-        /// Approves the account to transfer the specified token on behalf of the caller.
+        /// Sets the URI of the collection-level metadata. Only an admin can change
+        /// it.
         #[ink(message)]
-        pub fn approve(&mut self, to: AccountId, id: TokenId) -> Result<(), Error> {
-            self.approve_for(&to, id)?;
+        pub fn set_contract_uri(&mut self, contract_uri: String) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.ensure_metadata_not_frozen()?;
+            self.contract_uri.set(&contract_uri);
+            self.env().emit_event(ContractURIUpdated { contract_uri });
             Ok(())
         }
 
-        /// Transfers the token from the caller to the given destination.
+        /// Returns the accounts allowed to change token URIs.
         #[ink(message)]
-        pub fn transfer(
+        pub fn metadata_auth(&self) -> MetadataAuth {
+            self.metadata_auth.get_or_default()
+        }
+
+        /// Sets the accounts allowed to change token URIs. Only an admin can change
+        /// the setting.
+        #[ink(message)]
+        pub fn set_metadata_auth(
             &mut self,
-            destination: AccountId,
-            id: TokenId,
+            metadata_auth: MetadataAuth,
         ) -> Result<(), Error> {
-            let caller = self.env().caller();
-            self.transfer_token_from(&caller, &destination, id)?;
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.metadata_auth.set(&metadata_auth);
             Ok(())
         }
 
-        /// Transfer approved or owned token.
+        /// Returns `true` if the metadata of the token can no longer change.
         #[ink(message)]
-        pub fn transfer_from(
-            &mut self,
-            from: AccountId,
-            to: AccountId,
-            id: TokenId,
-        ) -> Result<(), Error> {
-            self.transfer_token_from(&from, &to, id)?;
+        pub fn is_metadata_frozen(&self, id: TokenId) -> bool {
+            self.metadata_frozen.get_or_default() || self.frozen_tokens.contains(id)
+        }
+
+        /// Returns `true` if the metadata of the whole collection can no longer
+        /// change.
+        #[ink(message)]
+        pub fn is_collection_metadata_frozen(&self) -> bool {
+            self.metadata_frozen.get_or_default()
+        }
+
+        /// Sets the URI of the token, or clears it to fall back to the base URI if
+        /// empty.
+        ///
+        /// The metadata authorization decides whether the owner, a metadata manager
+        /// or both can change the URI.
+        #[ink(message)]
+        pub fn set_token_uri(&mut self, id: TokenId, uri: TokenURI) -> Result<(), Error> {
+            self.ensure_metadata_auth(id)?;
+            if self.is_metadata_frozen(id) {
+                return Err(Error::MetadataFrozen);
+            }
+            if uri.is_empty() {
+                self.token_uris.remove(id);
+            } else {
+                self.token_uris.insert(id, &uri);
+            }
+            self.env().emit_event(MetadataUpdate { id });
             Ok(())
         }
 
-        /// Creates a new token.
+        /// Freezes the URI of the token for good, keeping the URI it currently
+        /// resolves to. Fails if the token does not resolve to any URI.
         #[ink(message)]
-        pub fn mint(&mut self, id: TokenId, url: TokenURI) -> Result<(), Error> {
-            let caller = self.env().caller();
-            self.add_token_to(&caller, id)?;
-            self.token_uris.insert(id, &url);
-            self.env().emit_event(Mint {
-                to: caller,
-                id,
-                uri: url.clone(),
+        pub fn freeze_token_metadata(&mut self, id: TokenId) -> Result<(), Error> {
+            self.ensure_metadata_auth(id)?;
+            if self.is_metadata_frozen(id) {
+                return Err(Error::MetadataFrozen);
+            }
+            let uri = self.token_uri(id).ok_or(Error::NoTokenURI)?;
+            self.token_uris.insert(id, &uri);
+            self.frozen_tokens.insert(id, &());
+            self.env().emit_event(PermanentURI {
+                value: uri,
+                id: Some(id),
             });
-            self.env().emit_event(Transfer {
-                from: Some(AccountId::from([0x0; 32])),
-                to: Some(caller),
-                id,
+            Ok(())
+        }
+
+        /// Freezes the metadata of the whole collection for good. Only an admin
+        /// can freeze the collection.
+        #[ink(message)]
+        pub fn freeze_metadata(&mut self) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.ensure_metadata_not_frozen()?;
+            self.metadata_frozen.set(&true);
+            self.env().emit_event(PermanentURI {
+                value: self.base_uri.get_or_default(),
+                id: None,
             });
             Ok(())
         }
 
-        /// Fetches the URI for a given token ID.
+        /// Returns the number of existing tokens.
         #[ink(message)]
-        pub fn token_uri(&self, id: TokenId) -> Option<TokenURI> {
-            self.token_uris.get(id)
+        pub fn total_supply(&self) -> u32 {
+            self.all_tokens_count
         }
 
-        /// Deletes an existing token. Only the owner can burn the token.
+        /// Returns the token at the given index of all existing tokens.
         #[ink(message)]
-        pub fn burn(&mut self, id: TokenId) -> Result<(), Error> {
-            let caller = self.env().caller();
-            let Self {
-                token_owner,
-                owned_tokens_count,
-                ..
-            } = self;
+        pub fn token_by_index(&self, index: u32) -> Option<TokenId> {
+            self.all_tokens.get(index)
+        }
 
-            let owner = token_owner.get(id).ok_or(Error::TokenNotFound)?;
-            if owner != caller {
-                return Err(Error::NotOwner);
-            };
+        /// Returns the token at the given index of the tokens owned by `owner`.
+        #[ink(message)]
+        pub fn token_of_owner_by_index(
+            &self,
+            owner: AccountId,
+            index: u32,
+        ) -> Option<TokenId> {
+            self.owned_tokens.get((owner, index))
+        }
 
-            let count = owned_tokens_count
-                .get(caller)
-                .map(|c| c.checked_sub(1).unwrap())
-                .ok_or(Error::CannotFetchValue)?;
-            owned_tokens_count.insert(caller, &count);
-            token_owner.remove(id);
-            self.token_uris.remove(id);
+        /// Returns the owners of the given tokens.
+        ///
+        /// At most `MAX_QUERY_SIZE` tokens can be queried at once.
+        #[ink(message)]
+        pub fn owners_of(
+            &self,
+            ids: Vec<TokenId>,
+        ) -> Result<Vec<Option<AccountId>>, Error> {
+            Self::ensure_query_size(ids.len())?;
+            Ok(ids.into_iter().map(|id| self.owner_of(id)).collect())
+        }
 
-            self.env().emit_event(Transfer {
-                from: Some(caller),
-                to: Some(AccountId::from([0x0; 32])),
-                id,
-            });
+        /// Returns the URIs of the given tokens.
+        ///
+        /// At most `MAX_QUERY_SIZE` tokens can be queried at once.
+        #[ink(message)]
+        pub fn token_uris_of(
+            &self,
+            ids: Vec<TokenId>,
+        ) -> Result<Vec<Option<TokenURI>>, Error> {
+            Self::ensure_query_size(ids.len())?;
+            Ok(ids.into_iter().map(|id| self.token_uri(id)).collect())
+        }
 
-            Ok(())
+        /// Returns a page of the tokens owned by `owner`.
+        ///
+        /// The page starts at owner index `start` and holds at most `limit` tokens,
+        /// capped at `MAX_QUERY_SIZE`.
+        #[ink(message)]
+        pub fn tokens_of_owner(
+            &self,
+            owner: AccountId,
+            start: u32,
+            limit: u32,
+        ) -> Vec<TokenId> {
+            let end = start
+                .saturating_add(limit.min(MAX_QUERY_SIZE))
+                .min(self.balance_of_or_zero(&owner));
+            (start..end)
+                .filter_map(|index| self.token_of_owner_by_index(owner, index))
+                .collect()
         }
 
-        /// Transfers token `id` `from` the sender to the `to` `AccountId`.
-        fn transfer_token_from(
+        /// Returns `true` if the account has been granted the role.
+        #[ink(message)]
+        pub fn has_role(&self, role: Role, account: AccountId) -> bool {
+            self.roles.contains((role, account))
+        }
+
+        /// Grants the role to the account. Only an admin can grant roles.
+        #[ink(message)]
+        pub fn grant_role(
             &mut self,
-            from: &AccountId,
-            to: &AccountId,
-            id: TokenId,
+            role: Role,
+            account: AccountId,
         ) -> Result<(), Error> {
             let caller = self.env().caller();
-            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
-            if !self.approved_or_owner(caller, id, owner) {
-                return Err(Error::NotApproved);
-            };
-            if owner != *from {
-                return Err(Error::NotOwner);
-            };
-            self.clear_approval(id);
-            self.remove_token_from(from, id)?;
-            self.add_token_to(to, id)?;
-            self.env().emit_event(Transfer {
-                from: Some(*from),
-                to: Some(*to),
-                id,
-            });
+            self.ensure_role(Role::Admin, caller)?;
+            if !self.has_role(role, account) {
+                self.roles.insert((role, account), &());
+                self.env().emit_event(RoleGranted {
+                    role,
+                    account,
+                    sender: caller,
+                });
+            }
             Ok(())
         }
 
-        /// Removes token `id` from the owner.
-        fn remove_token_from(
+        /// Revokes the role from the account. Only an admin can revoke roles.
+        #[ink(message)]
+        pub fn revoke_role(
             &mut self,
-            from: &AccountId,
-            id: TokenId,
+            role: Role,
+            account: AccountId,
         ) -> Result<(), Error> {
-            let Self {
-                token_owner,
-                owned_tokens_count,
-                ..
-            } = self;
+            let caller = self.env().caller();
+            self.ensure_role(Role::Admin, caller)?;
+            self.remove_role(role, account, caller);
+            Ok(())
+        }
 
-            if !token_owner.contains(id) {
-                return Err(Error::TokenNotFound);
-            }
+        /// Gives up the role held by the caller.
+        #[ink(message)]
+        pub fn renounce_role(&mut self, role: Role) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.ensure_role(role, caller)?;
+            self.remove_role(role, caller, caller);
+            Ok(())
+        }
 
-            let count = owned_tokens_count
-                .get(from)
-                .map(|c| c.checked_sub(1).unwrap())
-                .ok_or(Error::CannotFetchValue)?;
-            owned_tokens_count.insert(from, &count);
-            token_owner.remove(id);
+        /// Returns `true` if the operation is paused, either by itself or through
+        /// `Operation::All`.
+        #[ink(message)]
+        pub fn is_paused(&self, operation: Operation) -> bool {
+            self.paused.contains(Operation::All) || self.paused.contains(operation)
+        }
 
+        /// Pauses the operation. Only an admin can pause operations.
+        #[ink(message)]
+        pub fn pause(&mut self, operation: Operation) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.ensure_role(Role::Admin, caller)?;
+            if !self.paused.contains(operation) {
+                self.paused.insert(operation, &());
+                self.env().emit_event(Paused {
+                    operation,
+                    account: caller,
+                });
+            }
             Ok(())
         }
 
-        /// Adds the token `id` to the `to` AccountID.
-        fn add_token_to(&mut self, to: &AccountId, id: TokenId) -> Result<(), Error> {
-            let Self {
-                token_owner,
-                owned_tokens_count,
-                ..
-            } = self;
+        /// Unpauses the operation. Only an admin can unpause operations.
+        ///
+        /// Unpausing a single operation has no effect while `Operation::All` is paused.
+        #[ink(message)]
+        pub fn unpause(&mut self, operation: Operation) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.ensure_role(Role::Admin, caller)?;
+            if self.paused.contains(operation) {
+                self.paused.remove(operation);
+                self.env().emit_event(Unpaused {
+                    operation,
+                    account: caller,
+                });
+            }
+            Ok(())
+        }
 
-            if token_owner.contains(id) {
-                return Err(Error::TokenExists);
+        /// Returns the storage layout version the storage was last migrated to.
+        #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version
+        }
+
+        /// Replaces the code of the contract, keeping its storage.
+        ///
+        /// `migrate` must be called afterwards if the new code bumps
+        /// `STORAGE_VERSION`. Only an admin can upgrade the contract.
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|_| Error::UpgradeFailed)?;
+            self.env().emit_event(Upgraded { code_hash });
+            Ok(())
+        }
+
+        /// Migrates the storage from its stored layout version to `STORAGE_VERSION`.
+        ///
+        /// Only an admin can migrate the storage.
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            let from_version = self.storage_version;
+            if from_version > STORAGE_VERSION {
+                return Err(Error::InvalidStorageVersion);
+            }
+            if from_version == STORAGE_VERSION {
+                return Ok(());
             }
 
-            if *to == AccountId::from([0x0; 32]) {
-                return Err(Error::NotAllowed);
-            };
+            // Each layout change adds a step migrating from the previous version here.
 
-            let count = owned_tokens_count
-                .get(to)
-                .map(|c| c.checked_add(1).unwrap())
-                .unwrap_or(1);
+            self.storage_version = STORAGE_VERSION;
+            self.env().emit_event(Migrated {
+                from_version,
+                to_version: STORAGE_VERSION,
+            });
+            Ok(())
+        }
 
-            owned_tokens_count.insert(to, &count);
-            token_owner.insert(id, to);
+        /// Returns `true` if the token is soulbound and cannot be transferred.
+        #[ink(message)]
+        pub fn locked(&self, id: TokenId) -> bool {
+            self.soulbound_tokens.contains(id)
+        }
+
+        /// Returns the accounts allowed to burn the token.
+        #[ink(message)]
+        pub fn burn_auth(&self, id: TokenId) -> BurnAuth {
+            self.token_burn_auths
+                .get(id)
+                .unwrap_or(self.default_burn_auth.get_or_default())
+        }
 
+        /// Sets whether newly minted tokens are soulbound. Only an admin can
+        /// change the setting.
+        #[ink(message)]
+        pub fn set_soulbound_by_default(&mut self, soulbound: bool) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.soulbound_by_default.set(&soulbound);
             Ok(())
         }
 
-        /// Approves or disapproves the operator to transfer all tokens of the caller.
-        fn approve_for_all(
+        /// Sets the burn authorization of tokens without one of their own. Only an
+        /// admin can change the setting.
+        #[ink(message)]
+        pub fn set_default_burn_auth(
             &mut self,
-            to: AccountId,
-            approved: bool,
+            burn_auth: BurnAuth,
         ) -> Result<(), Error> {
-            let caller = self.env().caller();
-            if to == caller {
-                return Err(Error::NotAllowed);
-            }
-            self.env().emit_event(ApprovalForAll {
-                owner: caller,
-                operator: to,
-                approved,
-            });
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.default_burn_auth.set(&burn_auth);
+            Ok(())
+        }
 
-            if approved {
-                self.operator_approvals.insert((&caller, &to), &());
-            } else {
-                self.operator_approvals.remove((&caller, &to));
+        /// Creates a new soulbound token with its own burn authorization for the
+        /// `to` `AccountId`. Only a minter can create tokens.
+        #[ink(message)]
+        pub fn mint_soulbound(
+            &mut self,
+            to: AccountId,
+            id: TokenId,
+            url: TokenURI,
+            burn_auth: BurnAuth,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Minter, self.env().caller())?;
+            self.mint_token(&to, id, url)?;
+            if !self.locked(id) {
+                self.lock_token(id);
             }
-
+            self.token_burn_auths.insert(id, &burn_auth);
             Ok(())
         }
 
-        /// Approve the passed `AccountId` to transfer the specified token on behalf of
-        /// the message's sender.
-        fn approve_for(&mut self, to: &AccountId, id: TokenId) -> Result<(), Error> {
-            let caller = self.env().caller();
-            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
-            if !(owner == caller || self.approved_for_all(owner, caller)) {
-                return Err(Error::NotAllowed);
-            };
+        /// Returns the maximum number of tokens that can ever be minted, if capped.
+        #[ink(message)]
+        pub fn max_supply(&self) -> Option<u32> {
+            self.max_supply.get_or_default()
+        }
 
-            if *to == AccountId::from([0x0; 32]) {
-                return Err(Error::NotAllowed);
-            };
+        /// Returns the number of tokens minted so far.
+        #[ink(message)]
+        pub fn total_minted(&self) -> u32 {
+            self.total_minted.get_or_default()
+        }
 
-            if self.token_approvals.contains(id) {
-                return Err(Error::CannotInsert);
-            } else {
-                self.token_approvals.insert(id, to);
-            }
+        /// Returns the number of tokens burned so far.
+        #[ink(message)]
+        pub fn total_burned(&self) -> u32 {
+            self.total_burned.get_or_default()
+        }
 
-            self.env().emit_event(Approval {
-                from: caller,
-                to: *to,
-                id,
-            });
+        /// Returns the number of tokens minted to the account so far.
+        #[ink(message)]
+        pub fn minted_count(&self, account: AccountId) -> u32 {
+            self.minted_count.get(account).unwrap_or(0)
+        }
 
+        /// Sets the maximum number of tokens minted to a single account, or
+        /// removes the limit. Only an admin can change the limit.
+        #[ink(message)]
+        pub fn set_mint_limit(&mut self, limit: Option<u32>) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.mint_limit.set(&limit);
             Ok(())
         }
 
-        /// Removes existing approval from token `id`.
-        fn clear_approval(&mut self, id: TokenId) {
-            self.token_approvals.remove(id);
+        /// Sets the times from which minting is open and closed. `None` leaves that
+        /// side of the window unrestricted. Only an admin can change the window.
+        #[ink(message)]
+        pub fn set_mint_window(
+            &mut self,
+            start: Option<Timestamp>,
+            end: Option<Timestamp>,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.mint_start.set(&start);
+            self.mint_end.set(&end);
+            Ok(())
         }
 
-        /// Returns the total number of tokens from an account.
-        fn balance_of_or_zero(&self, of: &AccountId) -> u32 {
-            self.owned_tokens_count.get(of).unwrap_or(0)
+        /// Returns the sr25519 public key signing mint vouchers, if any.
+        #[ink(message)]
+        pub fn voucher_signer(&self) -> Option<[u8; 32]> {
+            self.voucher_signer.get_or_default()
         }
 
-        /// Gets an operator on other Account's behalf.
-        fn approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
-            self.operator_approvals.contains((&owner, &operator))
+        /// Returns `true` if a mint voucher with the nonce has been redeemed.
+        #[ink(message)]
+        pub fn is_voucher_nonce_used(&self, nonce: u64) -> bool {
+            self.used_voucher_nonces.contains(nonce)
         }
 
-        /// Returns true if the `AccountId` `from` is the owner of token `id`
-        /// or it has been approved on behalf of the token `id` owner.
-        fn approved_or_owner(
-            &self,
-            from: AccountId,
-            id: TokenId,
-            owner: AccountId,
-        ) -> bool {
-            from != AccountId::from([0x0; 32])
-                && (from == owner
-                || self.token_approvals.get(id) == Some(from)
-                || self.approved_for_all(owner, from))
+        /// Sets the sr25519 public key signing mint vouchers, or disables vouchers.
+        /// Only an admin can change the signer.
+        #[ink(message)]
+        pub fn set_voucher_signer(
+            &mut self,
+            signer: Option<[u8; 32]>,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.voucher_signer.set(&signer);
+            Ok(())
         }
-    }
 
-    /// Unit tests
-    #[cfg(test)]
-    mod tests {
-        /// Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
+        /// Creates the token described by a voucher signed by the voucher signer.
+        ///
+        /// The signature covers the SCALE encoding of the contract account ID
+        /// followed by `(b"voucher", voucher)`. Anyone can redeem a voucher until
+        /// its expiry.
+        ///
+        /// Relies on `sr25519_verify`, which is part of the unstable host interface.
+        #[ink(message)]
+        pub fn mint_with_voucher(
+            &mut self,
+            voucher: MintVoucher,
+            signature: [u8; 64],
+        ) -> Result<(), Error> {
+            let signer = self
+                .voucher_signer
+                .get_or_default()
+                .ok_or(Error::InvalidSignature)?;
+            self.check_voucher(&voucher)?;
+            let payload = self.voucher_payload(&voucher);
+            ink::env::sr25519_verify(&signature, &payload, &signer)
+                .map_err(|_| Error::InvalidSignature)?;
 
-        #[ink::test]
-        fn mint_works() {
-            let accounts =
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            // Create a new contract instance.
-            let mut erc721 = Erc721::new();
-            // Token 1 does not exist.
-            assert_eq!(erc721.owner_of(1), None);
-            // Alice does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.alice), 0);
-            // Create token Id 1 with a URI.
-            let token_uri = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri.clone()), Ok(()));
-            // Alice owns 1 token.
-            assert_eq!(erc721.balance_of(accounts.alice), 1);
-            // Check token URI.
-            assert_eq!(erc721.token_uri(1), Some(token_uri));
+            self.redeem_voucher(voucher)
         }
 
-        #[ink::test]
-        fn mint_existing_should_fail() {
-            let accounts =
-                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            // Create a new contract instance.
-            let mut erc721 = Erc721::new();
-            // Create token Id 1 with a URI.
-            let token_uri = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri.clone()), Ok(()));
-            // The first Transfer event takes place
-            assert_eq!(1, ink::env::test::recorded_events().count());
-            // Alice owns 1 token.
-            assert_eq!(erc721.balance_of(accounts.alice), 1);
-            // Alice owns token Id 1.
-            assert_eq!(erc721.owner_of(1), Some(accounts.alice));
-            // Cannot create token Id if it exists.
+        /// Returns `true` if the Ethereum address has been granted the role.
+        #[ink(message)]
+        pub fn has_eth_role(&self, role: Role, address: EthAddress) -> bool {
+            self.eth_roles.contains((role, address))
+        }
+
+        /// Grants the role to the Ethereum address. Only an admin can grant roles.
+        #[ink(message)]
+        pub fn grant_eth_role(
+            &mut self,
+            role: Role,
+            address: EthAddress,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.ensure_role(Role::Admin, caller)?;
+            if !self.has_eth_role(role, address) {
+                self.eth_roles.insert((role, address), &());
+                self.env().emit_event(EthRoleGranted {
+                    role,
+                    address,
+                    sender: caller,
+                });
+            }
+            Ok(())
+        }
+
+        /// Revokes the role from the Ethereum address. Only an admin can revoke
+        /// roles.
+        #[ink(message)]
+        pub fn revoke_eth_role(
+            &mut self,
+            role: Role,
+            address: EthAddress,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.ensure_role(Role::Admin, caller)?;
+            if self.has_eth_role(role, address) {
+                self.eth_roles.remove((role, address));
+                self.env().emit_event(EthRoleRevoked {
+                    role,
+                    address,
+                    sender: caller,
+                });
+            }
+            Ok(())
+        }
+
+        /// Creates the token described by a voucher signed with the secp256k1 key
+        /// of an Ethereum address holding the minter role.
+        ///
+        /// The voucher is signed like with `mint_with_voucher`, but through the
+        /// Ethereum `personal_sign` scheme over the Keccak-256 hash of the payload.
+        #[ink(message)]
+        pub fn mint_with_ecdsa_voucher(
+            &mut self,
+            voucher: MintVoucher,
+            signature: [u8; 65],
+        ) -> Result<(), Error> {
+            self.check_voucher(&voucher)?;
+            let payload = self.voucher_payload(&voucher);
+            let public_key = self.ecdsa_recover_signer(&payload, &signature)?;
+            let signer = self
+                .env()
+                .ecdsa_to_eth_address(&public_key)
+                .map_err(|_| Error::InvalidSignature)?;
+            if !self.has_eth_role(Role::Minter, signer) {
+                return Err(Error::MissingRole);
+            }
+
+            self.redeem_voucher(voucher)
+        }
+
+        /// Returns the nonce expected by the next permit of the token.
+        #[ink(message)]
+        pub fn permit_nonce(&self, id: TokenId) -> u64 {
+            self.permit_nonces.get(id).unwrap_or(0)
+        }
+
+        /// Approves `spender` for the token with a signature of its owner, so anyone
+        /// can submit the approval on the owner's behalf.
+        ///
+        /// The owner signs the SCALE encoding of the contract account ID followed by
+        /// `(b"permit", spender, id, nonce, deadline)`, where `nonce` is the current
+        /// `permit_nonce` of the token. The permit is valid until `deadline`.
+        ///
+        /// Relies on `sr25519_verify`, which is part of the unstable host interface.
+        #[ink(message)]
+        pub fn permit(
+            &mut self,
+            spender: AccountId,
+            id: TokenId,
+            deadline: Timestamp,
+            signature: [u8; 64],
+        ) -> Result<(), Error> {
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::SignatureExpired);
+            }
+            let payload = self.permit_payload(spender, id, deadline);
+            ink::env::sr25519_verify(&signature, &payload, owner.as_ref())
+                .map_err(|_| Error::InvalidSignature)?;
+
+            self.increment_permit_nonce(id);
+            self.approve_for(owner, &spender, id)
+        }
+
+        /// Approves `spender` for the token with a secp256k1 signature of its owner.
+        ///
+        /// The owner account must be derived from the signing key, i.e. be the
+        /// BLAKE2-256 hash of its compressed public key. The permit is signed like
+        /// with `permit`, but through the Ethereum `personal_sign` scheme over the
+        /// Keccak-256 hash of the payload.
+        #[ink(message)]
+        pub fn permit_ecdsa(
+            &mut self,
+            spender: AccountId,
+            id: TokenId,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<(), Error> {
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::SignatureExpired);
+            }
+            let payload = self.permit_payload(spender, id, deadline);
+            let public_key = self.ecdsa_recover_signer(&payload, &signature)?;
+            let signer = self.env().hash_bytes::<Blake2x256>(&public_key);
+            if AccountId::from(signer) != owner {
+                return Err(Error::InvalidSignature);
+            }
+
+            self.increment_permit_nonce(id);
+            self.approve_for(owner, &spender, id)
+        }
+
+        /// Returns the nonce expected by the next signed operation of the signer.
+        #[ink(message)]
+        pub fn signed_operation_nonce(&self, signer: AccountId) -> u64 {
+            self.signed_operation_nonces.get(signer).unwrap_or(0)
+        }
+
+        /// Returns the balance the account deposited to pay relayer fees.
+        #[ink(message)]
+        pub fn fee_deposit(&self, account: AccountId) -> Balance {
+            self.fee_deposits.get(account).unwrap_or(0)
+        }
+
+        /// Deposits the transferred value to pay relayer fees of the caller.
+        #[ink(message, payable)]
+        pub fn deposit_fees(&mut self) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let deposit = self
+                .fee_deposit(caller)
+                .checked_add(self.env().transferred_value())
+                .ok_or(Error::CannotInsert)?;
+            self.fee_deposits.insert(caller, &deposit);
+            Ok(())
+        }
+
+        /// Withdraws `amount` from the fee deposit of the caller.
+        #[ink(message)]
+        pub fn withdraw_fees(&mut self, amount: Balance) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let deposit = self
+                .fee_deposit(caller)
+                .checked_sub(amount)
+                .ok_or(Error::InsufficientDeposit)?;
+            self.fee_deposits.insert(caller, &deposit);
+            self.pay(caller, amount)
+        }
+
+        /// Executes an operation signed by `signer` as if `signer` were the caller.
+        ///
+        /// The signer signs the SCALE encoding of the contract account ID followed by
+        /// `(b"execute", operation, nonce, fee)`, where `nonce` is the current
+        /// `signed_operation_nonce` of the signer. A non-zero `fee` is paid to the
+        /// caller out of the fee deposit of the signer.
+        ///
+        /// Relies on `sr25519_verify`, which is part of the unstable host interface.
+        #[ink(message)]
+        pub fn execute_signed(
+            &mut self,
+            operation: SignedOperation,
+            signer: AccountId,
+            signature: [u8; 64],
+            nonce: u64,
+            fee: Balance,
+        ) -> Result<(), Error> {
+            if nonce != self.signed_operation_nonce(signer) {
+                return Err(Error::InvalidNonce);
+            }
+            let payload = self.signed_operation_payload(&operation, nonce, fee);
+            ink::env::sr25519_verify(&signature, &payload, signer.as_ref())
+                .map_err(|_| Error::InvalidSignature)?;
+            let next_nonce = nonce.checked_add(1).ok_or(Error::CannotInsert)?;
+            self.signed_operation_nonces.insert(signer, &next_nonce);
+
+            match operation {
+                SignedOperation::Transfer { from, to, id } => {
+                    self.transfer_token_from(signer, &from, &to, id)?
+                }
+                SignedOperation::Approve { to, id } => self.approve_for(signer, &to, id)?,
+                SignedOperation::SetApprovalForAll { operator, approved } => {
+                    self.approve_for_all(signer, operator, approved)?
+                }
+                SignedOperation::Burn { id } => self.burn_token(signer, id)?,
+            }
+
+            let relayer = self.env().caller();
+            if fee > 0 {
+                let deposit = self
+                    .fee_deposit(signer)
+                    .checked_sub(fee)
+                    .ok_or(Error::InsufficientDeposit)?;
+                self.fee_deposits.insert(signer, &deposit);
+                self.pay(relayer, fee)?;
+            }
+            self.env().emit_event(SignedOperationExecuted {
+                signer,
+                relayer,
+                nonce,
+                fee,
+            });
+            Ok(())
+        }
+
+        /// Returns the listing of the token, if listed.
+        #[ink(message)]
+        pub fn listing(&self, id: TokenId) -> Option<Listing> {
+            self.listings.get(id)
+        }
+
+        /// Returns the marketplace fee receiver and basis points, if charged.
+        #[ink(message)]
+        pub fn marketplace_fee(&self) -> Option<(AccountId, u16)> {
+            let basis_points = self.marketplace_fee_basis_points.get_or_default();
+            self.marketplace_fee_receiver
+                .get_or_default()
+                .map(|receiver| (receiver, basis_points))
+        }
+
+        /// Sets the marketplace fee charged on sales, or removes it. Only an admin
+        /// can change the fee.
+        #[ink(message)]
+        pub fn set_marketplace_fee(
+            &mut self,
+            receiver: Option<AccountId>,
+            basis_points: u16,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            if basis_points > BASIS_POINTS {
+                return Err(Error::FeesExceedPrice);
+            }
+            self.marketplace_fee_receiver.set(&receiver);
+            self.marketplace_fee_basis_points.set(&basis_points);
+            Ok(())
+        }
+
+        /// Lists the token of the caller for sale at `price`, replacing any
+        /// previous listing.
+        ///
+        /// The listing is invalidated once the token is transferred or burned.
+        #[ink(message)]
+        pub fn list(&mut self, id: TokenId, price: Balance) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if owner != caller {
+                return Err(Error::NotOwner);
+            }
+            if self.locked(id) {
+                return Err(Error::Soulbound);
+            }
+            self.listings.insert(
+                id,
+                &Listing {
+                    seller: caller,
+                    price,
+                },
+            );
+            self.env().emit_event(Listed {
+                id,
+                seller: caller,
+                price,
+            });
+            Ok(())
+        }
+
+        /// Cancels the listing of the token. Only the seller can cancel it, even
+        /// while transfers are paused.
+        #[ink(message)]
+        pub fn cancel_listing(&mut self, id: TokenId) -> Result<(), Error> {
+            let listing = self.listing(id).ok_or(Error::NotListed)?;
+            if listing.seller != self.env().caller() {
+                return Err(Error::NotOwner);
+            }
+            self.remove_listing(id);
+            Ok(())
+        }
+
+        /// Buys the listed token for the transferred value, which must match the
+        /// listed price.
+        ///
+        /// The royalty and the marketplace fee are paid out of the price and the
+        /// rest goes to the seller.
+        #[ink(message, payable)]
+        pub fn buy(&mut self, id: TokenId) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let buyer = self.env().caller();
+            let listing = self.listing(id).ok_or(Error::NotListed)?;
+            if self.env().transferred_value() != listing.price {
+                return Err(Error::IncorrectPayment);
+            }
+            if buyer == listing.seller {
+                return Err(Error::NotAllowed);
+            }
+
+            self.listings.remove(id);
+            self.transfer_token_from(listing.seller, &listing.seller, &buyer, id)?;
+            self.pay_out_sale(id, listing.seller, listing.price)?;
+            self.env().emit_event(Sold {
+                id,
+                seller: listing.seller,
+                buyer,
+                price: listing.price,
+            });
+            Ok(())
+        }
+
+        /// Returns the running auction of the token, if any.
+        #[ink(message)]
+        pub fn auction(&self, id: TokenId) -> Option<Auction> {
+            self.auctions.get(id)
+        }
+
+        /// Returns the current price of the token in a Dutch auction.
+        #[ink(message)]
+        pub fn dutch_auction_price(&self, id: TokenId) -> Option<Balance> {
+            let auction = self.auction(id)?;
+            match auction.kind {
+                AuctionKind::Dutch {
+                    start_price,
+                    end_price,
+                } => {
+                    let duration = Balance::from(auction.end - auction.start);
+                    let elapsed = Balance::from(
+                        self.env()
+                            .block_timestamp()
+                            .clamp(auction.start, auction.end)
+                            - auction.start,
+                    );
+                    let drop = start_price - end_price;
+                    // Split the drop to keep the multiplication from overflowing.
+                    let dropped = (drop / duration) * elapsed
+                        + (drop % duration) * elapsed / duration;
+                    Some(start_price - dropped)
+                }
+                AuctionKind::English { .. } => None,
+            }
+        }
+
+        /// Puts the token of the caller up for an English auction lasting
+        /// `duration` milliseconds.
+        ///
+        /// The token is escrowed by the contract until the auction is settled.
+        #[ink(message)]
+        pub fn start_english_auction(
+            &mut self,
+            id: TokenId,
+            reserve_price: Balance,
+            duration: Timestamp,
+        ) -> Result<(), Error> {
+            self.start_auction(id, AuctionKind::English { reserve_price }, duration)
+        }
+
+        /// Puts the token of the caller up for a Dutch auction whose price falls
+        /// from `start_price` to `end_price` over `duration` milliseconds.
+        ///
+        /// The token is escrowed by the contract until the auction is settled.
+        #[ink(message)]
+        pub fn start_dutch_auction(
+            &mut self,
+            id: TokenId,
+            start_price: Balance,
+            end_price: Balance,
+            duration: Timestamp,
+        ) -> Result<(), Error> {
+            if end_price > start_price {
+                return Err(Error::InvalidAuction);
+            }
+            let kind = AuctionKind::Dutch {
+                start_price,
+                end_price,
+            };
+            self.start_auction(id, kind, duration)
+        }
+
+        /// Bids the transferred value in the English auction of the token,
+        /// refunding the previous highest bidder.
+        ///
+        /// A bid placed within `AUCTION_EXTENSION` of the end extends the auction.
+        #[ink(message, payable)]
+        pub fn bid(&mut self, id: TokenId) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let bidder = self.env().caller();
+            let amount = self.env().transferred_value();
+            let now = self.env().block_timestamp();
+            let mut auction = self.auction(id).ok_or(Error::NoAuction)?;
+            let AuctionKind::English { reserve_price } = auction.kind else {
+                return Err(Error::NoAuction);
+            };
+            if now >= auction.end {
+                return Err(Error::AuctionEnded);
+            }
+            if bidder == auction.seller {
+                return Err(Error::NotAllowed);
+            }
+            let outbid = auction.highest_bid.map_or(0, |bid| bid.amount);
+            if amount < reserve_price || amount <= outbid {
+                return Err(Error::BidTooLow);
+            }
+
+            let previous = auction.highest_bid.replace(Bid { bidder, amount });
+            auction.end = auction.end.max(now + AUCTION_EXTENSION);
+            self.auctions.insert(id, &auction);
+            if let Some(previous) = previous {
+                self.pay(previous.bidder, previous.amount)?;
+            }
+            self.env().emit_event(BidPlaced {
+                id,
+                bidder,
+                amount,
+                end: auction.end,
+            });
+            Ok(())
+        }
+
+        /// Buys the token in its Dutch auction at the current price, refunding
+        /// any value transferred above it.
+        #[ink(message, payable)]
+        pub fn buy_at_auction(&mut self, id: TokenId) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let buyer = self.env().caller();
+            let auction = self.auction(id).ok_or(Error::NoAuction)?;
+            let price = self.dutch_auction_price(id).ok_or(Error::NoAuction)?;
+            if self.env().block_timestamp() >= auction.end {
+                return Err(Error::AuctionEnded);
+            }
+            let change = self
+                .env()
+                .transferred_value()
+                .checked_sub(price)
+                .ok_or(Error::IncorrectPayment)?;
+            if buyer == auction.seller {
+                return Err(Error::NotAllowed);
+            }
+
+            self.auctions.remove(id);
+            self.release_from_escrow(id, buyer)?;
+            self.pay(buyer, change)?;
+            self.pay_out_sale(id, auction.seller, price)?;
+            self.env().emit_event(AuctionSettled {
+                id,
+                winner: Some(buyer),
+                price,
+            });
+            Ok(())
+        }
+
+        /// Settles the ended auction of the token, handing it to the highest
+        /// bidder or back to the seller when there was no winning bid. Anyone
+        /// can settle an auction.
+        #[ink(message)]
+        pub fn settle_auction(&mut self, id: TokenId) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let auction = self.auction(id).ok_or(Error::NoAuction)?;
+            if self.env().block_timestamp() < auction.end {
+                return Err(Error::AuctionNotEnded);
+            }
+            self.auctions.remove(id);
+            self.close_auction(id, auction)
+        }
+
+        /// Cancels the auction of the token and returns it to the seller. Only
+        /// the seller can cancel an auction, and only before any bid. An auction
+        /// can be cancelled while transfers are paused.
+        #[ink(message)]
+        pub fn cancel_auction(&mut self, id: TokenId) -> Result<(), Error> {
+            let auction = self.auction(id).ok_or(Error::NoAuction)?;
+            if auction.seller != self.env().caller() {
+                return Err(Error::NotOwner);
+            }
+            if auction.highest_bid.is_some() {
+                return Err(Error::NotAllowed);
+            }
+            self.auctions.remove(id);
+            self.close_auction(id, auction)
+        }
+
+        /// Returns the open offer with the given ID, if any.
+        #[ink(message)]
+        pub fn offer(&self, offer_id: OfferId) -> Option<Offer> {
+            self.offers.get(offer_id)
+        }
+
+        /// Offers the transferred value for token `id` until `expiry` and returns
+        /// the offer ID.
+        ///
+        /// The funds stay escrowed until the offer is accepted or cancelled.
+        #[ink(message, payable)]
+        pub fn make_offer(
+            &mut self,
+            id: TokenId,
+            expiry: Timestamp,
+        ) -> Result<OfferId, Error> {
+            if self.owner_of(id).is_none() {
+                return Err(Error::TokenNotFound);
+            }
+            self.place_offer(Some(id), expiry)
+        }
+
+        /// Offers the transferred value for any token of the collection until
+        /// `expiry` and returns the offer ID.
+        ///
+        /// The funds stay escrowed until the offer is accepted or cancelled.
+        #[ink(message, payable)]
+        pub fn make_collection_offer(
+            &mut self,
+            expiry: Timestamp,
+        ) -> Result<OfferId, Error> {
+            self.place_offer(None, expiry)
+        }
+
+        /// Cancels the offer and refunds its funds to the buyer. The buyer can
+        /// cancel an offer at any time, even while transfers are paused, and
+        /// anyone else once it has expired.
+        #[ink(message)]
+        pub fn cancel_offer(&mut self, offer_id: OfferId) -> Result<(), Error> {
+            let offer = self.offer(offer_id).ok_or(Error::NoOffer)?;
+            if offer.buyer != self.env().caller()
+                && self.env().block_timestamp() < offer.expiry
+            {
+                return Err(Error::NotAllowed);
+            }
+            self.offers.remove(offer_id);
+            self.pay(offer.buyer, offer.amount)?;
+            self.env().emit_event(OfferCancelled { offer_id });
+            Ok(())
+        }
+
+        /// Accepts the offer for a token of the caller, selling it for the
+        /// escrowed funds.
+        #[ink(message)]
+        pub fn accept_offer(&mut self, offer_id: OfferId) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let offer = self.offer(offer_id).ok_or(Error::NoOffer)?;
+            let id = offer.id.ok_or(Error::NoOffer)?;
+            self.accept(offer_id, offer, id)
+        }
+
+        /// Accepts the collection offer, selling token `id` of the caller for the
+        /// escrowed funds.
+        #[ink(message)]
+        pub fn accept_collection_offer(
+            &mut self,
+            offer_id: OfferId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let offer = self.offer(offer_id).ok_or(Error::NoOffer)?;
+            if offer.id.is_some() {
+                return Err(Error::NoOffer);
+            }
+            self.accept(offer_id, offer, id)
+        }
+
+        /// Returns the pending swap with the given ID, if any.
+        #[ink(message)]
+        pub fn swap(&self, swap_id: SwapId) -> Option<Swap> {
+            self.swaps.get(swap_id)
+        }
+
+        /// Proposes to give the `offered` tokens of the caller and the transferred
+        /// value to `counterparty` in exchange for their `requested` tokens and
+        /// `requested_value`, and returns the swap ID.
+        ///
+        /// The transferred value stays escrowed until the swap is accepted or
+        /// cancelled. The offered tokens stay with the caller, who must approve the
+        /// contract to transfer them until the swap is accepted.
+        #[ink(message, payable)]
+        pub fn propose_swap(
+            &mut self,
+            counterparty: AccountId,
+            offered: Vec<TokenId>,
+            requested: Vec<TokenId>,
+            requested_value: Balance,
+        ) -> Result<SwapId, Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let proposer = self.env().caller();
+            if proposer == counterparty {
+                return Err(Error::NotAllowed);
+            }
+            Self::ensure_query_size(offered.len())?;
+            Self::ensure_query_size(requested.len())?;
+            self.ensure_swappable(proposer, &offered)?;
+            self.ensure_swappable(counterparty, &requested)?;
+            self.ensure_approved_for_swap(proposer, &offered)?;
+
+            let swap_id = self.last_swap_id.get_or_default().checked_add(1).unwrap();
+            self.last_swap_id.set(&swap_id);
+            let swap = Swap {
+                proposer,
+                counterparty,
+                offered,
+                requested,
+                offered_value: self.env().transferred_value(),
+                requested_value,
+            };
+            self.swaps.insert(swap_id, &swap);
+            self.env().emit_event(SwapProposed {
+                swap_id,
+                proposer,
+                counterparty,
+                offered: swap.offered,
+                requested: swap.requested,
+                offered_value: swap.offered_value,
+                requested_value,
+            });
+            Ok(swap_id)
+        }
+
+        /// Accepts the swap, paying the requested value with the transferred value.
+        ///
+        /// All tokens and value change hands at once; the swap fails entirely if
+        /// any token changed owner since it was proposed, or if the proposer no
+        /// longer approves the contract to transfer the offered tokens.
+        #[ink(message, payable)]
+        pub fn accept_swap(&mut self, swap_id: SwapId) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let swap = self.swap(swap_id).ok_or(Error::NoSwap)?;
+            if swap.counterparty != self.env().caller() {
+                return Err(Error::NotAllowed);
+            }
+            if self.env().transferred_value() != swap.requested_value {
+                return Err(Error::IncorrectPayment);
+            }
+            self.ensure_swappable(swap.proposer, &swap.offered)?;
+            self.ensure_swappable(swap.counterparty, &swap.requested)?;
+            self.ensure_approved_for_swap(swap.proposer, &swap.offered)?;
+
+            self.swaps.remove(swap_id);
+            let escrow = self.env().account_id();
+            for &id in &swap.offered {
+                let (from, to) = (swap.proposer, swap.counterparty);
+                self.transfer_token_from(escrow, &from, &to, id)?;
+            }
+            for &id in &swap.requested {
+                let (from, to) = (swap.counterparty, swap.proposer);
+                self.transfer_token_from(from, &from, &to, id)?;
+            }
+            self.pay(swap.counterparty, swap.offered_value)?;
+            self.pay(swap.proposer, swap.requested_value)?;
+            self.env().emit_event(SwapExecuted { swap_id });
+            Ok(())
+        }
+
+        /// Cancels the swap and refunds the escrowed value to the proposer. Either
+        /// party can cancel a swap, even while transfers are paused.
+        #[ink(message)]
+        pub fn cancel_swap(&mut self, swap_id: SwapId) -> Result<(), Error> {
+            let swap = self.swap(swap_id).ok_or(Error::NoSwap)?;
+            let caller = self.env().caller();
+            if caller != swap.proposer && caller != swap.counterparty {
+                return Err(Error::NotAllowed);
+            }
+            self.swaps.remove(swap_id);
+            self.pay(swap.proposer, swap.offered_value)?;
+            self.env().emit_event(SwapCancelled { swap_id });
+            Ok(())
+        }
+
+        /// Returns the user of the token, or `None` if there is none or the user
+        /// role has expired.
+        #[ink(message)]
+        pub fn user_of(&self, id: TokenId) -> Option<AccountId> {
+            self.token_users
+                .get(id)
+                .filter(|info| self.env().block_timestamp() <= info.expires)
+                .map(|info| info.user)
+        }
+
+        /// Returns the time the user role of the token expires, if it has a user.
+        #[ink(message)]
+        pub fn user_expires(&self, id: TokenId) -> Option<Timestamp> {
+            self.token_users.get(id).map(|info| info.expires)
+        }
+
+        /// Grants `user` the user role of the token until `expires`, or clears it
+        /// if `None`. Only the owner or an approved account can set the user.
+        ///
+        /// The user role is cleared when the token is transferred.
+        #[ink(message)]
+        pub fn set_user(
+            &mut self,
+            id: TokenId,
+            user: Option<AccountId>,
+            expires: Timestamp,
+        ) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Approve)?;
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if !self.approved_or_owner(self.env().caller(), id, owner) {
+                return Err(Error::NotApproved);
+            }
+            match user {
+                Some(user) => {
+                    self.token_users.insert(id, &UserInfo { user, expires });
+                }
+                None => self.token_users.remove(id),
+            }
+            self.env().emit_event(UpdateUser { id, user, expires });
+            Ok(())
+        }
+
+        /// Returns the code hash of the share contract used for vaulted tokens.
+        #[ink(message)]
+        pub fn share_code_hash(&self) -> Option<Hash> {
+            self.share_code_hash.get_or_default()
+        }
+
+        /// Sets the code hash of the share contract used for vaulted tokens, or
+        /// disables fractionalization if `None`. Only an admin can set it.
+        #[ink(message)]
+        pub fn set_share_code_hash(
+            &mut self,
+            code_hash: Option<Hash>,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.share_code_hash.set(&code_hash);
+            Ok(())
+        }
+
+        /// Returns the share contract of the token, if it is vaulted.
+        #[ink(message)]
+        pub fn shares_of(&self, id: TokenId) -> Option<AccountId> {
+            self.vaults.get(id)
+        }
+
+        /// Locks the token of the caller in the vault and returns a new PSP22
+        /// share contract minting `supply` shares to the caller.
+        ///
+        /// The supply must not be zero, or anyone could redeem the token.
+        #[ink(message)]
+        pub fn fractionalize(
+            &mut self,
+            id: TokenId,
+            supply: Balance,
+        ) -> Result<AccountId, Error> {
+            let caller = self.env().caller();
+            if supply == 0 {
+                return Err(Error::InvalidSupply);
+            }
+            let code_hash = self
+                .share_code_hash
+                .get_or_default()
+                .ok_or(Error::NoShareCode)?;
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if owner != caller {
+                return Err(Error::NotOwner);
+            }
+
+            let salt = self.vaults_created.get_or_default();
+            self.vaults_created.set(&salt.checked_add(1).unwrap());
+            let shares = SharesRef::new(caller, supply)
+                .code_hash(code_hash)
+                .endowment(0)
+                .salt_bytes(salt.to_le_bytes())
+                .try_instantiate()
+                .map_err(|_| Error::SharesCallFailed)?
+                .map_err(|_| Error::SharesCallFailed)?;
+            let shares = ink::ToAccountId::<Environment>::to_account_id(&shares);
+            self.lock_in_vault(caller, id, shares, supply)?;
+            Ok(shares)
+        }
+
+        /// Burns all shares of the vaulted token, which must be held by the
+        /// caller, and releases the token to the caller. Like other withdrawals,
+        /// redeeming is allowed while transfers are paused.
+        #[ink(message)]
+        pub fn redeem(&mut self, id: TokenId) -> Result<(), Error> {
+            let holder = self.env().caller();
+            let shares = self.shares_of(id).ok_or(Error::NotFractionalized)?;
+            self.burn_all_shares(shares, holder)?;
+            self.release_from_vault(id, holder)
+        }
+
+        /// Creates a new token. Only a minter can create tokens.
+        #[ink(message)]
+        pub fn mint(&mut self, id: TokenId, url: TokenURI) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.ensure_role(Role::Minter, caller)?;
+            self.mint_token(&caller, id, url)
+        }
+
+        /// Creates a new token for the `to` `AccountId`. Only a minter can create
+        /// tokens.
+        #[ink(message)]
+        pub fn mint_to(
+            &mut self,
+            to: AccountId,
+            id: TokenId,
+            url: TokenURI,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Minter, self.env().caller())?;
+            self.mint_token(&to, id, url)
+        }
+
+        /// Creates a new token with the next free token ID for the `to` `AccountId`.
+        ///
+        /// Returns the ID of the new token. Only a minter can create tokens.
+        #[ink(message)]
+        pub fn mint_next(
+            &mut self,
+            to: AccountId,
+            url: TokenURI,
+        ) -> Result<TokenId, Error> {
+            self.ensure_role(Role::Minter, self.env().caller())?;
+            // Skip IDs that were taken by minting an explicit ID.
+            let mut id = self.last_token_id.get_or_default();
+            loop {
+                id = id.checked_add(1).ok_or(Error::CannotInsert)?;
+                if !self.token_owner.contains(id) {
+                    break;
+                }
+            }
+            self.mint_token(&to, id, url)?;
+            self.last_token_id.set(&id);
+            Ok(id)
+        }
+
+        /// Creates a new token with its own royalty for the `to` `AccountId`. Only a
+        /// minter can create tokens.
+        #[ink(message)]
+        pub fn mint_with_royalty(
+            &mut self,
+            to: AccountId,
+            id: TokenId,
+            url: TokenURI,
+            receiver: AccountId,
+            basis_points: u16,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Minter, self.env().caller())?;
+            let royalty = Self::royalty(receiver, basis_points)?;
+            self.mint_token(&to, id, url)?;
+            self.token_royalties.insert(id, &royalty);
+            self.env().emit_event(RoyaltyUpdated {
+                id: Some(id),
+                royalty: Some(royalty),
+            });
+            Ok(())
+        }
+
+        /// Returns the royalty receiver and amount owed for a sale of the token.
+        ///
+        /// The token royalty takes precedence over the default royalty. Without
+        /// either, the zero account and an amount of zero are returned.
+        #[ink(message)]
+        pub fn royalty_info(
+            &self,
+            id: TokenId,
+            sale_price: Balance,
+        ) -> (AccountId, Balance) {
+            match self.token_royalties.get(id).or(self.default_royalty) {
+                Some(royalty) => (
+                    royalty.receiver,
+                    Self::basis_points_of(sale_price, royalty.basis_points),
+                ),
+                None => (AccountId::from([0x0; 32]), 0),
+            }
+        }
+
+        /// Sets the royalty applied to tokens without a royalty of their own.
+        /// Only an admin can set royalties.
+        #[ink(message)]
+        pub fn set_default_royalty(
+            &mut self,
+            receiver: AccountId,
+            basis_points: u16,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            let royalty = Self::royalty(receiver, basis_points)?;
+            self.default_royalty = Some(royalty);
+            self.env().emit_event(RoyaltyUpdated {
+                id: None,
+                royalty: Some(royalty),
+            });
+            Ok(())
+        }
+
+        /// Removes the default royalty. Only an admin can remove royalties.
+        #[ink(message)]
+        pub fn delete_default_royalty(&mut self) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.default_royalty = None;
+            self.env().emit_event(RoyaltyUpdated {
+                id: None,
+                royalty: None,
+            });
+            Ok(())
+        }
+
+        /// Sets the royalty of an existing token. Only an admin can set royalties.
+        #[ink(message)]
+        pub fn set_token_royalty(
+            &mut self,
+            id: TokenId,
+            receiver: AccountId,
+            basis_points: u16,
+        ) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            if !self.token_owner.contains(id) {
+                return Err(Error::TokenNotFound);
+            }
+            let royalty = Self::royalty(receiver, basis_points)?;
+            self.token_royalties.insert(id, &royalty);
+            self.env().emit_event(RoyaltyUpdated {
+                id: Some(id),
+                royalty: Some(royalty),
+            });
+            Ok(())
+        }
+
+        /// Removes the royalty of a token so the default royalty applies again.
+        /// Only an admin can remove royalties.
+        #[ink(message)]
+        pub fn reset_token_royalty(&mut self, id: TokenId) -> Result<(), Error> {
+            self.ensure_role(Role::Admin, self.env().caller())?;
+            self.token_royalties.remove(id);
+            self.env().emit_event(RoyaltyUpdated {
+                id: Some(id),
+                royalty: None,
+            });
+            Ok(())
+        }
+
+        /// Deletes an existing token.
+        ///
+        /// The burn authorization of the token decides whether the owner, a burner,
+        /// both or neither can burn it.
+        #[ink(message)]
+        pub fn burn(&mut self, id: TokenId) -> Result<(), Error> {
+            self.burn_token(self.env().caller(), id)
+        }
+
+        /// Burns token `id` on behalf of `caller`.
+        fn burn_token(&mut self, caller: AccountId, id: TokenId) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Burn)?;
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if self.escrowed(id) {
+                return Err(Error::Escrowed);
+            }
+            let is_owner = owner == caller;
+            let is_burner = self.has_role(Role::Burner, caller);
+            let allowed = match self.burn_auth(id) {
+                BurnAuth::IssuerOnly => is_burner,
+                BurnAuth::OwnerOnly => is_owner,
+                BurnAuth::Both => is_owner || is_burner,
+                BurnAuth::Neither => false,
+            };
+            if !allowed {
+                return Err(if is_owner {
+                    Error::NotAllowed
+                } else {
+                    Error::NotOwner
+                });
+            };
+
+            self.remove_token_from(&owner, id)?;
+            self.remove_token_from_all_tokens(id);
+            let total_burned = self.total_burned.get_or_default();
+            self.total_burned.set(&total_burned.checked_add(1).unwrap());
+            self.clear_approval(id);
+            self.remove_listing(id);
+            self.clear_user(id);
+            self.token_uris.remove(id);
+            self.frozen_tokens.remove(id);
+            self.token_royalties.remove(id);
+            self.soulbound_tokens.remove(id);
+            self.token_burn_auths.remove(id);
+            self.permit_nonces.remove(id);
+
+            self.env().emit_event(Transfer {
+                from: Some(owner),
+                to: None,
+                id,
+            });
+            self.env().emit_event(psp34::Transfer {
+                from: Some(owner),
+                to: None,
+                id: Id::U32(id),
+            });
+
+            Ok(())
+        }
+
+        /// Creates token `id` with the given URI for the `to` `AccountId`. An empty
+        /// URI leaves the token to the base URI.
+        fn mint_token(
+            &mut self,
+            to: &AccountId,
+            id: TokenId,
+            url: TokenURI,
+        ) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Mint)?;
+            let now = self.env().block_timestamp();
+            if self.mint_start.get_or_default().is_some_and(|start| now < start)
+                || self.mint_end.get_or_default().is_some_and(|end| now >= end)
+            {
+                return Err(Error::MintClosed);
+            }
+            let total_minted = self.total_minted.get_or_default();
+            if self.max_supply.get_or_default().is_some_and(|max| total_minted >= max) {
+                return Err(Error::SupplyExceeded);
+            }
+            let minted = self.minted_count(*to);
+            if self.mint_limit.get_or_default().is_some_and(|limit| minted >= limit) {
+                return Err(Error::MintLimitReached);
+            }
+
+            self.add_token_to(to, id)?;
+            self.add_token_to_all_tokens(id);
+            self.total_minted.set(&total_minted.checked_add(1).unwrap());
+            self.minted_count.insert(to, &minted.checked_add(1).unwrap());
+            if !url.is_empty() {
+                self.token_uris.insert(id, &url);
+            }
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(*to),
+                id,
+            });
+            self.env().emit_event(psp34::Transfer {
+                from: None,
+                to: Some(*to),
+                id: Id::U32(id),
+            });
+            if self.soulbound_by_default.get_or_default() {
+                self.lock_token(id);
+            }
+            Ok(())
+        }
+
+        /// Makes token `id` soulbound.
+        fn lock_token(&mut self, id: TokenId) {
+            self.soulbound_tokens.insert(id, &());
+            self.env().emit_event(Locked { id });
+        }
+
+        /// Transfers token `id` `from` the sender to the `to` `AccountId` on behalf
+        /// of `caller`.
+        fn transfer_token_from(
+            &mut self,
+            caller: AccountId,
+            from: &AccountId,
+            to: &AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            self.move_token_from(caller, from, to, id)
+        }
+
+        /// Moves token `id` like `transfer_token_from`, even while transfers are
+        /// paused.
+        fn move_token_from(
+            &mut self,
+            caller: AccountId,
+            from: &AccountId,
+            to: &AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if !self.approved_or_owner(caller, id, owner) {
+                return Err(Error::NotApproved);
+            };
+            if self.locked(id) {
+                return Err(Error::Soulbound);
+            };
+            if owner != *from {
+                return Err(Error::NotOwner);
+            };
+            self.clear_approval(id);
+            self.remove_listing(id);
+            self.clear_user(id);
+            self.increment_permit_nonce(id);
+            self.remove_token_from(from, id)?;
+            self.add_token_to(to, id)?;
+            self.env().emit_event(Transfer {
+                from: Some(*from),
+                to: Some(*to),
+                id,
+            });
+            self.env().emit_event(psp34::Transfer {
+                from: Some(*from),
+                to: Some(*to),
+                id: Id::U32(id),
+            });
+            Ok(())
+        }
+
+        /// Calls the receiver hook of the `to` contract for token `id`.
+        fn check_on_erc721_received(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+            data: Vec<u8>,
+        ) -> Result<(), Error> {
+            let mut receiver: ink::contract_ref!(Erc721Receiver) = to.into();
+            let accepted = receiver
+                .call_mut()
+                .on_erc721_received(self.env().caller(), from, id, data)
+                .try_invoke();
+            match accepted {
+                Ok(Ok(true)) => Ok(()),
+                _ => Err(Error::SafeTransferCheckFailed),
+            }
+        }
+
+        /// Removes token `id` from the owner.
+        fn remove_token_from(
+            &mut self,
+            from: &AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            let Self {
+                token_owner,
+                owned_tokens_count,
+                owned_tokens,
+                owned_tokens_index,
+                ..
+            } = self;
+
+            if !token_owner.contains(id) {
+                return Err(Error::TokenNotFound);
+            }
+
+            let count = owned_tokens_count
+                .get(from)
+                .map(|c| c.checked_sub(1).unwrap())
+                .ok_or(Error::CannotFetchValue)?;
+
+            // Move the last owned token into the slot of the removed one.
+            let index = owned_tokens_index.get(id).ok_or(Error::CannotFetchValue)?;
+            if index != count {
+                let last = owned_tokens
+                    .get((from, count))
+                    .ok_or(Error::CannotFetchValue)?;
+                owned_tokens.insert((from, index), &last);
+                owned_tokens_index.insert(last, &index);
+            }
+            owned_tokens.remove((from, count));
+            owned_tokens_index.remove(id);
+
+            owned_tokens_count.insert(from, &count);
+            token_owner.remove(id);
+
+            Ok(())
+        }
+
+        /// Adds the token `id` to the `to` AccountID.
+        fn add_token_to(&mut self, to: &AccountId, id: TokenId) -> Result<(), Error> {
+            let Self {
+                token_owner,
+                owned_tokens_count,
+                owned_tokens,
+                owned_tokens_index,
+                ..
+            } = self;
+
+            if token_owner.contains(id) {
+                return Err(Error::TokenExists);
+            }
+
+            if *to == AccountId::from([0x0; 32]) {
+                return Err(Error::NotAllowed);
+            };
+
+            let index = owned_tokens_count.get(to).unwrap_or(0);
+            let count = index.checked_add(1).unwrap();
+
+            owned_tokens.insert((to, index), &id);
+            owned_tokens_index.insert(id, &index);
+            owned_tokens_count.insert(to, &count);
+            token_owner.insert(id, to);
+
+            Ok(())
+        }
+
+        /// Appends the token `id` to the enumeration of all tokens.
+        fn add_token_to_all_tokens(&mut self, id: TokenId) {
+            let index = self.all_tokens_count;
+            self.all_tokens.insert(index, &id);
+            self.all_tokens_index.insert(id, &index);
+            self.all_tokens_count = index.checked_add(1).unwrap();
+        }
+
+        /// Removes the token `id` from the enumeration of all tokens.
+        fn remove_token_from_all_tokens(&mut self, id: TokenId) {
+            let last_index = self.all_tokens_count.checked_sub(1).unwrap();
+            let index = self.all_tokens_index.get(id).unwrap();
+
+            // Move the last token into the slot of the removed one.
+            if index != last_index {
+                let last = self.all_tokens.get(last_index).unwrap();
+                self.all_tokens.insert(index, &last);
+                self.all_tokens_index.insert(last, &index);
+            }
+            self.all_tokens.remove(last_index);
+            self.all_tokens_index.remove(id);
+            self.all_tokens_count = last_index;
+        }
+
+        /// Approves or disapproves the operator to transfer all tokens of `caller`.
+        fn approve_for_all(
+            &mut self,
+            caller: AccountId,
+            to: AccountId,
+            approved: bool,
+        ) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Approve)?;
+            if to == caller {
+                return Err(Error::NotAllowed);
+            }
+            self.env().emit_event(ApprovalForAll {
+                owner: caller,
+                operator: to,
+                approved,
+            });
+            self.env().emit_event(psp34::Approval {
+                owner: caller,
+                operator: to,
+                id: None,
+                approved,
+            });
+
+            if approved {
+                self.operator_approvals.insert((&caller, &to), &());
+            } else {
+                self.operator_approvals.remove((&caller, &to));
+            }
+
+            Ok(())
+        }
+
+        /// Approve the passed `AccountId` to transfer the specified token on behalf of
+        /// `caller`.
+        fn approve_for(
+            &mut self,
+            caller: AccountId,
+            to: &AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Approve)?;
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if !(owner == caller || self.approved_for_all(owner, caller)) {
+                return Err(Error::NotAllowed);
+            };
+
+            if self.locked(id) {
+                return Err(Error::Soulbound);
+            };
+
+            if *to == AccountId::from([0x0; 32]) {
+                return Err(Error::NotAllowed);
+            };
+
+            if self.token_approvals.contains(id) {
+                return Err(Error::CannotInsert);
+            } else {
+                self.token_approvals.insert(id, to);
+            }
+
+            self.env().emit_event(Approval {
+                from: caller,
+                to: *to,
+                id,
+            });
+            self.env().emit_event(psp34::Approval {
+                owner,
+                operator: *to,
+                id: Some(Id::U32(id)),
+                approved: true,
+            });
+
+            Ok(())
+        }
+
+        /// Returns the payload signed off-chain to authorize `value` for this
+        /// contract.
+        fn signed_payload<T: Encode>(&self, value: &T) -> Vec<u8> {
+            (self.env().account_id(), value).encode()
+        }
+
+        /// Returns an error if the voucher has expired or has been redeemed.
+        fn check_voucher(&self, voucher: &MintVoucher) -> Result<(), Error> {
+            if self.env().block_timestamp() > voucher.expiry {
+                return Err(Error::SignatureExpired);
+            }
+            if self.is_voucher_nonce_used(voucher.nonce) {
+                return Err(Error::NonceAlreadyUsed);
+            }
+            Ok(())
+        }
+
+        /// Marks the voucher as redeemed and creates its token.
+        fn redeem_voucher(&mut self, voucher: MintVoucher) -> Result<(), Error> {
+            self.used_voucher_nonces.insert(voucher.nonce, &());
+            self.mint_token(&voucher.recipient, voucher.id, voucher.uri)
+        }
+
+        /// Returns the hash an Ethereum wallet signs for `payload` with
+        /// `personal_sign`, given the Keccak-256 hash of the payload as message.
+        fn eth_message_hash(&self, payload: &[u8]) -> [u8; 32] {
+            let hash = self.env().hash_bytes::<Keccak256>(payload);
+            let mut message = Vec::from(&b"\x19Ethereum Signed Message:\n32"[..]);
+            message.extend_from_slice(&hash);
+            self.env().hash_bytes::<Keccak256>(&message)
+        }
+
+        /// Recovers the compressed secp256k1 public key that signed `payload`.
+        fn ecdsa_recover_signer(
+            &self,
+            payload: &[u8],
+            signature: &[u8; 65],
+        ) -> Result<[u8; 33], Error> {
+            self.env()
+                .ecdsa_recover(signature, &self.eth_message_hash(payload))
+                .map_err(|_| Error::InvalidSignature)
+        }
+
+        /// Returns the payload signed to authorize minting `voucher`.
+        fn voucher_payload(&self, voucher: &MintVoucher) -> Vec<u8> {
+            self.signed_payload(&(b"voucher", voucher))
+        }
+
+        /// Returns the payload signed by the owner of token `id` to permit `spender`.
+        fn permit_payload(
+            &self,
+            spender: AccountId,
+            id: TokenId,
+            deadline: Timestamp,
+        ) -> Vec<u8> {
+            let nonce = self.permit_nonce(id);
+            self.signed_payload(&(b"permit", spender, id, nonce, deadline))
+        }
+
+        /// Returns the payload signed to relay `operation` through `execute_signed`.
+        fn signed_operation_payload(
+            &self,
+            operation: &SignedOperation,
+            nonce: u64,
+            fee: Balance,
+        ) -> Vec<u8> {
+            self.signed_payload(&(b"execute", operation, nonce, fee))
+        }
+
+        /// Invalidates all outstanding permits of token `id`.
+        fn increment_permit_nonce(&mut self, id: TokenId) {
+            let nonce = self.permit_nonce(id).wrapping_add(1);
+            self.permit_nonces.insert(id, &nonce);
+        }
+
+        /// Returns `true` if token `id` is held in escrow by the contract.
+        fn escrowed(&self, id: TokenId) -> bool {
+            self.auctions.contains(id) || self.vaults.contains(id)
+        }
+
+        /// Removes the listing of token `id`, if listed.
+        fn remove_listing(&mut self, id: TokenId) {
+            if self.listings.take(id).is_some() {
+                self.env().emit_event(Delisted { id });
+            }
+        }
+
+        /// Burns the whole supply of the `shares` contract, failing unless all of
+        /// it is held by `holder`.
+        fn burn_all_shares(
+            &self,
+            shares: AccountId,
+            holder: AccountId,
+        ) -> Result<(), Error> {
+            let mut shares: SharesRef =
+                ink::env::call::FromAccountId::from_account_id(shares);
+            let supply = PSP22::total_supply(shares.call()).try_invoke();
+            let Ok(Ok(supply)) = supply else {
+                return Err(Error::SharesCallFailed);
+            };
+            match shares.call_mut().burn(holder, supply).try_invoke() {
+                Ok(Ok(Ok(()))) => Ok(()),
+                Ok(Ok(Err(PSP22Error::InsufficientBalance))) => {
+                    Err(Error::InsufficientShares)
+                }
+                _ => Err(Error::SharesCallFailed),
+            }
+        }
+
+        /// Escrows the transferred value as an offer for token `id` or, if `None`,
+        /// any token.
+        fn place_offer(
+            &mut self,
+            id: Option<TokenId>,
+            expiry: Timestamp,
+        ) -> Result<OfferId, Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let buyer = self.env().caller();
+            let amount = self.env().transferred_value();
+            if amount == 0 {
+                return Err(Error::IncorrectPayment);
+            }
+            if self.env().block_timestamp() >= expiry {
+                return Err(Error::OfferExpired);
+            }
+
+            let offer_id = self.last_offer_id.get_or_default().checked_add(1).unwrap();
+            self.last_offer_id.set(&offer_id);
+            self.offers.insert(
+                offer_id,
+                &Offer {
+                    buyer,
+                    id,
+                    amount,
+                    expiry,
+                },
+            );
+            self.env().emit_event(OfferMade {
+                offer_id,
+                buyer,
+                id,
+                amount,
+                expiry,
+            });
+            Ok(offer_id)
+        }
+
+        /// Sells token `id` of the caller to the buyer of the offer for its
+        /// escrowed funds.
+        fn accept(
+            &mut self,
+            offer_id: OfferId,
+            offer: Offer,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            let seller = self.env().caller();
+            if self.env().block_timestamp() >= offer.expiry {
+                return Err(Error::OfferExpired);
+            }
+            if self.owner_of(id) != Some(seller) {
+                return Err(Error::NotOwner);
+            }
+            if seller == offer.buyer {
+                return Err(Error::NotAllowed);
+            }
+
+            self.offers.remove(offer_id);
+            self.transfer_token_from(seller, &seller, &offer.buyer, id)?;
+            self.pay_out_sale(id, seller, offer.amount)?;
+            self.env().emit_event(OfferAccepted {
+                offer_id,
+                id,
+                seller,
+                buyer: offer.buyer,
+                price: offer.amount,
+            });
+            Ok(())
+        }
+
+        /// Returns an error unless the distinct tokens are owned by `owner` and can
+        /// be transferred.
+        fn ensure_swappable(
+            &self,
+            owner: AccountId,
+            ids: &[TokenId],
+        ) -> Result<(), Error> {
+            for (index, &id) in ids.iter().enumerate() {
+                if ids[..index].contains(&id) {
+                    return Err(Error::NotAllowed);
+                }
+                if self.owner_of(id).ok_or(Error::TokenNotFound)? != owner {
+                    return Err(Error::NotOwner);
+                }
+                if self.locked(id) {
+                    return Err(Error::Soulbound);
+                }
+            }
+            Ok(())
+        }
+
+        /// Checks that `owner` approves the contract to transfer the tokens.
+        fn ensure_approved_for_swap(
+            &self,
+            owner: AccountId,
+            ids: &[TokenId],
+        ) -> Result<(), Error> {
+            let escrow = self.env().account_id();
+            if ids.iter().any(|&id| !self.approved_or_owner(escrow, id, owner)) {
+                return Err(Error::NotApproved);
+            }
+            Ok(())
+        }
+
+        /// Escrows token `id` of the caller and starts an auction of it.
+        fn start_auction(
+            &mut self,
+            id: TokenId,
+            kind: AuctionKind,
+            duration: Timestamp,
+        ) -> Result<(), Error> {
+            self.ensure_not_paused(Operation::Transfer)?;
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            if owner != caller {
+                return Err(Error::NotOwner);
+            }
+            if duration == 0 {
+                return Err(Error::InvalidAuction);
+            }
+
+            let escrow = self.env().account_id();
+            self.transfer_token_from(caller, &caller, &escrow, id)?;
+            let start = self.env().block_timestamp();
+            let end = start.checked_add(duration).ok_or(Error::InvalidAuction)?;
+            self.auctions.insert(
+                id,
+                &Auction {
+                    seller: caller,
+                    kind,
+                    start,
+                    end,
+                    highest_bid: None,
+                },
+            );
+            self.env().emit_event(AuctionStarted {
+                id,
+                seller: caller,
+                kind,
+                end,
+            });
+            Ok(())
+        }
+
+        /// Hands token `id` of a removed auction to its highest bidder, paying
+        /// out the bid, or back to the seller.
+        fn close_auction(&mut self, id: TokenId, auction: Auction) -> Result<(), Error> {
+            match auction.highest_bid {
+                Some(bid) => {
+                    self.release_from_escrow(id, bid.bidder)?;
+                    self.pay_out_sale(id, auction.seller, bid.amount)?;
+                    self.env().emit_event(AuctionSettled {
+                        id,
+                        winner: Some(bid.bidder),
+                        price: bid.amount,
+                    });
+                }
+                None => {
+                    self.release_from_escrow(id, auction.seller)?;
+                    self.env().emit_event(AuctionSettled {
+                        id,
+                        winner: None,
+                        price: 0,
+                    });
+                }
+            }
+            Ok(())
+        }
+
+        /// Transfers escrowed token `id` from the contract to the `to` `AccountId`.
+        /// Releasing is not subject to the pause, so that escrowed tokens can
+        /// always be returned.
+        fn release_from_escrow(
+            &mut self,
+            id: TokenId,
+            to: AccountId,
+        ) -> Result<(), Error> {
+            let escrow = self.env().account_id();
+            self.move_token_from(escrow, &escrow, &to, id)
+        }
+
+        /// Escrows token `id` of `holder` in the vault backed by `supply` shares
+        /// of the `shares` contract.
+        fn lock_in_vault(
+            &mut self,
+            holder: AccountId,
+            id: TokenId,
+            shares: AccountId,
+            supply: Balance,
+        ) -> Result<(), Error> {
+            let escrow = self.env().account_id();
+            self.transfer_token_from(holder, &holder, &escrow, id)?;
+            self.vaults.insert(id, &shares);
+            self.env().emit_event(Fractionalized {
+                id,
+                shares,
+                holder,
+                supply,
+            });
+            Ok(())
+        }
+
+        /// Releases vaulted token `id` to `holder` once all its shares are burned.
+        fn release_from_vault(
+            &mut self,
+            id: TokenId,
+            holder: AccountId,
+        ) -> Result<(), Error> {
+            self.vaults.remove(id);
+            self.release_from_escrow(id, holder)?;
+            self.env().emit_event(Redeemed { id, holder });
+            Ok(())
+        }
+
+        /// Pays out the proceeds of selling token `id` for `price` to `seller`,
+        /// after paying the royalty and the marketplace fee. The fee is capped at
+        /// what is left of the price after the royalty, so the fees in force at
+        /// the time of the sale can never make it fail.
+        fn pay_out_sale(
+            &mut self,
+            id: TokenId,
+            seller: AccountId,
+            price: Balance,
+        ) -> Result<(), Error> {
+            let (royalty_receiver, royalty) = self.royalty_info(id, price);
+            let rest = price.saturating_sub(royalty);
+            let (fee_receiver, fee) = match self.marketplace_fee() {
+                Some((receiver, basis_points)) => {
+                    (receiver, Self::basis_points_of(price, basis_points).min(rest))
+                }
+                None => (seller, 0),
+            };
+            self.pay(royalty_receiver, royalty)?;
+            self.pay(fee_receiver, fee)?;
+            self.pay(seller, rest - fee)
+        }
+
+        /// Transfers `amount` from the contract balance to the `to` `AccountId`.
+        fn pay(&self, to: AccountId, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Ok(());
+            }
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| Error::TransferFailed)
+        }
+
+        /// Returns the share of `amount` given in basis points.
+        fn basis_points_of(amount: Balance, basis_points: u16) -> Balance {
+            let basis_points = Balance::from(basis_points);
+            let denominator = Balance::from(BASIS_POINTS);
+            // Split the amount to keep the multiplication from overflowing.
+            (amount / denominator) * basis_points
+                + (amount % denominator) * basis_points / denominator
+        }
+
+        /// Returns a validated royalty for the receiver.
+        fn royalty(receiver: AccountId, basis_points: u16) -> Result<RoyaltyInfo, Error> {
+            if basis_points > BASIS_POINTS {
+                return Err(Error::InvalidRoyalty);
+            }
+            Ok(RoyaltyInfo {
+                receiver,
+                basis_points,
+            })
+        }
+
+        /// Returns an error if a bulk query exceeds `MAX_QUERY_SIZE` entries.
+        fn ensure_query_size(len: usize) -> Result<(), Error> {
+            if len > MAX_QUERY_SIZE as usize {
+                return Err(Error::QueryTooLarge);
+            }
+            Ok(())
+        }
+
+        /// Returns an error unless the caller may change the URI of token `id`.
+        fn ensure_metadata_auth(&self, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let owner = self.owner_of(id).ok_or(Error::TokenNotFound)?;
+            let is_owner = owner == caller;
+            let is_manager = self.has_role(Role::MetadataManager, caller);
+            let allowed = match self.metadata_auth.get_or_default() {
+                MetadataAuth::ManagerOnly => is_manager,
+                MetadataAuth::OwnerOnly => is_owner,
+                MetadataAuth::Both => is_owner || is_manager,
+            };
+            if !allowed {
+                return Err(if is_owner {
+                    Error::NotAllowed
+                } else {
+                    Error::NotOwner
+                });
+            }
+            Ok(())
+        }
+
+        /// Returns an error if the collection metadata is frozen.
+        fn ensure_metadata_not_frozen(&self) -> Result<(), Error> {
+            if self.metadata_frozen.get_or_default() {
+                return Err(Error::MetadataFrozen);
+            }
+            Ok(())
+        }
+
+        /// Returns an error if the operation is paused.
+        fn ensure_not_paused(&self, operation: Operation) -> Result<(), Error> {
+            if self.is_paused(operation) {
+                return Err(Error::Paused);
+            }
+            Ok(())
+        }
+
+        /// Returns an error if the account has not been granted the role.
+        fn ensure_role(&self, role: Role, account: AccountId) -> Result<(), Error> {
+            if !self.has_role(role, account) {
+                return Err(Error::MissingRole);
+            }
+            Ok(())
+        }
+
+        /// Removes the role from the account, if granted.
+        fn remove_role(&mut self, role: Role, account: AccountId, sender: AccountId) {
+            if self.has_role(role, account) {
+                self.roles.remove((role, account));
+                self.env().emit_event(RoleRevoked {
+                    role,
+                    account,
+                    sender,
+                });
+            }
+        }
+
+        /// Removes existing approval from token `id`.
+        fn clear_approval(&mut self, id: TokenId) {
+            self.token_approvals.remove(id);
+        }
+
+        /// Clears the user of token `id`, if any.
+        fn clear_user(&mut self, id: TokenId) {
+            if self.token_users.take(id).is_some() {
+                self.env().emit_event(UpdateUser {
+                    id,
+                    user: None,
+                    expires: 0,
+                });
+            }
+        }
+
+        /// Returns the total number of tokens from an account.
+        fn balance_of_or_zero(&self, of: &AccountId) -> u32 {
+            self.owned_tokens_count.get(of).unwrap_or(0)
+        }
+
+        /// Gets an operator on other Account's behalf.
+        fn approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.operator_approvals.contains((&owner, &operator))
+        }
+
+        /// Returns true if the `AccountId` `from` is the owner of token `id`
+        /// or it has been approved on behalf of the token `id` owner.
+        fn approved_or_owner(
+            &self,
+            from: AccountId,
+            id: TokenId,
+            owner: AccountId,
+        ) -> bool {
+            from != AccountId::from([0x0; 32])
+                && (from == owner
+                || self.token_approvals.get(id) == Some(from)
+                || self.approved_for_all(owner, from))
+        }
+
+        /// Returns the token ID of a PSP34 ID, if it fits in a `TokenId`.
+        fn token_id(id: Id) -> Option<TokenId> {
+            match id {
+                Id::U8(id) => Some(id.into()),
+                Id::U16(id) => Some(id.into()),
+                Id::U32(id) => Some(id),
+                Id::U64(id) => id.try_into().ok(),
+                Id::U128(id) => id.try_into().ok(),
+                Id::Bytes(_) => None,
+            }
+        }
+    }
+
+    impl Erc721Core for Erc721 {
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> u32 {
+            self.balance_of_or_zero(&owner)
+        }
+
+        #[ink(message)]
+        fn owner_of(&self, id: TokenId) -> Option<AccountId> {
+            self.token_owner.get(id)
+        }
+
+        #[ink(message)]
+        fn transfer(&mut self, destination: AccountId, id: TokenId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.transfer_token_from(caller, &caller, &destination, id)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+        ) -> Result<(), Error> {
+            self.transfer_token_from(self.env().caller(), &from, &to, id)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn safe_transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+            data: Vec<u8>,
+        ) -> Result<(), Error> {
+            self.transfer_token_from(self.env().caller(), &from, &to, id)?;
+            if self.env().is_contract(&to) {
+                self.check_on_erc721_received(from, to, id, data)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Erc721Approvals for Erc721 {
+        #[ink(message)]
+        fn get_approved(&self, id: TokenId) -> Option<AccountId> {
+            self.token_approvals.get(id)
+        }
+
+        #[ink(message)]
+        fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            self.approved_for_all(owner, operator)
+        }
+
+        #[ink(message)]
+        fn set_approval_for_all(
+            &mut self,
+            to: AccountId,
+            approved: bool,
+        ) -> Result<(), Error> {
+            self.approve_for_all(self.env().caller(), to, approved)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn approve(&mut self, to: AccountId, id: TokenId) -> Result<(), Error> {
+            self.approve_for(self.env().caller(), &to, id)?;
+            Ok(())
+        }
+    }
+
+    impl Erc721Metadata for Erc721 {
+        #[ink(message)]
+        fn name(&self) -> String {
+            self.name.get_or_default()
+        }
+
+        #[ink(message)]
+        fn symbol(&self) -> String {
+            self.symbol.get_or_default()
+        }
+
+        #[ink(message)]
+        fn token_uri(&self, id: TokenId) -> Option<TokenURI> {
+            self.token_uris.get(id).or_else(|| {
+                let mut uri = self.base_uri.get_or_default();
+                if uri.is_empty() || !self.token_owner.contains(id) {
+                    return None;
+                }
+                uri.push_str(&id.to_string());
+                Some(uri)
+            })
+        }
+    }
+
+    impl erc721_traits::PSP34 for Erc721 {
+        #[ink(message)]
+        fn collection_id(&self) -> Id {
+            let account = self.env().account_id();
+            Id::Bytes(AsRef::<[u8]>::as_ref(&account).to_vec())
+        }
+
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> u32 {
+            self.balance_of_or_zero(&owner)
+        }
+
+        #[ink(message)]
+        fn owner_of(&self, id: Id) -> Option<AccountId> {
+            Self::token_id(id).and_then(|id| self.token_owner.get(id))
+        }
+
+        #[ink(message)]
+        fn allowance(
+            &self,
+            owner: AccountId,
+            operator: AccountId,
+            id: Option<Id>,
+        ) -> bool {
+            if self.approved_for_all(owner, operator) {
+                return true;
+            }
+            let Some(id) = id.and_then(Self::token_id) else {
+                return false;
+            };
+            self.token_owner.get(id) == Some(owner)
+                && self.token_approvals.get(id) == Some(operator)
+        }
+
+        #[ink(message)]
+        fn approve(
+            &mut self,
+            operator: AccountId,
+            id: Option<Id>,
+            approved: bool,
+        ) -> Result<(), PSP34Error> {
+            let caller = self.env().caller();
+            let Some(id) = id else {
+                if operator == caller {
+                    return Err(PSP34Error::SelfApprove);
+                }
+                return Ok(self.approve_for_all(caller, operator, approved)?);
+            };
+            let id = Self::token_id(id).ok_or(PSP34Error::TokenNotExists)?;
+            if self.token_owner.get(id) == Some(operator) {
+                return Err(PSP34Error::SelfApprove);
+            }
+            if approved {
+                return Ok(self.approve_for(caller, &operator, id)?);
+            }
+            self.ensure_not_paused(Operation::Approve)?;
+            let owner = self.token_owner.get(id).ok_or(PSP34Error::TokenNotExists)?;
+            if !(owner == caller || self.approved_for_all(owner, caller)) {
+                return Err(PSP34Error::NotApproved);
+            }
+            if self.token_approvals.get(id) == Some(operator) {
+                self.clear_approval(id);
+            }
+            self.env().emit_event(psp34::Approval {
+                owner,
+                operator,
+                id: Some(Id::U32(id)),
+                approved: false,
+            });
+            Ok(())
+        }
+
+        /// Transfers the token like `Erc721Core::transfer`. This is not a safe
+        /// transfer: `data` is ignored and receiving contracts are not asked to
+        /// accept the token. Use `safe_transfer_from` for that.
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            id: Id,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP34Error> {
+            let caller = self.env().caller();
+            let id = Self::token_id(id).ok_or(PSP34Error::TokenNotExists)?;
+            Ok(self.transfer_token_from(caller, &caller, &to, id)?)
+        }
+
+        #[ink(message)]
+        fn total_supply(&self) -> u128 {
+            self.all_tokens_count.into()
+        }
+    }
+
+    /// Unit tests
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        #[ink::test]
+        fn mint_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Token 1 does not exist.
+            assert_eq!(erc721.owner_of(1), None);
+            // Alice does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.alice), 0);
+            // Create token Id 1 with a URI.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri.clone()), Ok(()));
+            // Alice owns 1 token.
+            assert_eq!(erc721.balance_of(accounts.alice), 1);
+            // Check token URI.
+            assert_eq!(erc721.token_uri(1), Some(token_uri));
+        }
+
+        #[ink::test]
+        fn mint_existing_should_fail() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 with a URI.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri.clone()), Ok(()));
+            // The first Transfer events take place after the four role grants
+            assert_eq!(6, ink::env::test::recorded_events().count());
+            // Alice owns 1 token.
+            assert_eq!(erc721.balance_of(accounts.alice), 1);
+            // Alice owns token Id 1.
+            assert_eq!(erc721.owner_of(1), Some(accounts.alice));
+            // Cannot create token Id if it exists.
             // Bob cannot own token Id 1.
             assert_eq!(erc721.mint(1, token_uri), Err(Error::TokenExists));
         }
 
         #[ink::test]
-        fn transfer_works() {
+        fn transfer_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Alice
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Alice owns token 1
+            assert_eq!(erc721.balance_of(accounts.alice), 1);
+            // Bob does not own any token
+            assert_eq!(erc721.balance_of(accounts.bob), 0);
+            // The first Transfer events take place after the four role grants
+            assert_eq!(6, ink::env::test::recorded_events().count());
+            // Alice transfers token 1 to Bob
+            assert_eq!(erc721.transfer(accounts.bob, 1), Ok(()));
+            // The second Transfer events take place
+            assert_eq!(8, ink::env::test::recorded_events().count());
+            // Bob owns token 1
+            assert_eq!(erc721.balance_of(accounts.bob), 1);
+        }
+
+        #[ink::test]
+        fn invalid_transfer_should_fail() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Transfer token fails if it does not exist.
+            assert_eq!(erc721.transfer(accounts.bob, 2), Err(Error::TokenNotFound));
+            // Token Id 2 does not exist.
+            assert_eq!(erc721.owner_of(2), None);
+            // Create token Id 2.
+            let token_uri = String::from("https://example.com/nft/2");
+            assert_eq!(erc721.mint(2, token_uri), Ok(()));
+            // Alice owns 1 token.
+            assert_eq!(erc721.balance_of(accounts.alice), 1);
+            // Token Id 2 is owned by Alice.
+            assert_eq!(erc721.owner_of(2), Some(accounts.alice));
+            // Set Bob as caller
+            set_caller(accounts.bob);
+            // Bob cannot transfer not owned tokens.
+            assert_eq!(erc721.transfer(accounts.eve, 2), Err(Error::NotApproved));
+        }
+
+        #[ink::test]
+        fn approved_transfer_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Token Id 1 is owned by Alice.
+            assert_eq!(erc721.owner_of(1), Some(accounts.alice));
+            // Approve token Id 1 transfer for Bob on behalf of Alice.
+            assert_eq!(erc721.approve(accounts.bob, 1), Ok(()));
+            // Set Bob as caller
+            set_caller(accounts.bob);
+            // Bob transfers token Id 1 from Alice to Eve.
+            assert_eq!(
+                erc721.transfer_from(accounts.alice, accounts.eve, 1),
+                Ok(())
+            );
+            // Token Id 1 is owned by Eve.
+            assert_eq!(erc721.owner_of(1), Some(accounts.eve));
+            // Alice does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.alice), 0);
+            // Bob does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.bob), 0);
+            // Eve owns 1 token.
+            assert_eq!(erc721.balance_of(accounts.eve), 1);
+        }
+
+        #[ink::test]
+        fn approved_for_all_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1.
+            let token_uri1 = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri1), Ok(()));
+            // Create token Id 2.
+            let token_uri2 = String::from("https://example.com/nft/2");
+            assert_eq!(erc721.mint(2, token_uri2), Ok(()));
+            // Alice owns 2 tokens.
+            assert_eq!(erc721.balance_of(accounts.alice), 2);
+            // Approve token Id 1 transfer for Bob on behalf of Alice.
+            assert_eq!(erc721.set_approval_for_all(accounts.bob, true), Ok(()));
+            // Bob is an approved operator for Alice
+            assert!(erc721.is_approved_for_all(accounts.alice, accounts.bob));
+            // Set Bob as caller
+            set_caller(accounts.bob);
+            // Bob transfers token Id 1 from Alice to Eve.
+            assert_eq!(
+                erc721.transfer_from(accounts.alice, accounts.eve, 1),
+                Ok(())
+            );
+            // Token Id 1 is owned by Eve.
+            assert_eq!(erc721.owner_of(1), Some(accounts.eve));
+            // Alice owns 1 token.
+            assert_eq!(erc721.balance_of(accounts.alice), 1);
+            // Bob transfers token Id 2 from Alice to Eve.
+            assert_eq!(
+                erc721.transfer_from(accounts.alice, accounts.eve, 2),
+                Ok(())
+            );
+            // Bob does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.bob), 0);
+            // Eve owns 2 tokens.
+            assert_eq!(erc721.balance_of(accounts.eve), 2);
+            // Remove operator approval for Bob on behalf of Alice.
+            set_caller(accounts.alice);
+            assert_eq!(erc721.set_approval_for_all(accounts.bob, false), Ok(()));
+            // Bob is not an approved operator for Alice.
+            assert!(!erc721.is_approved_for_all(accounts.alice, accounts.bob));
+        }
+
+        #[ink::test]
+        fn approve_nonexistent_token_should_fail() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Approve transfer of nonexistent token id 1
+            assert_eq!(erc721.approve(accounts.bob, 1), Err(Error::TokenNotFound));
+        }
+
+        #[ink::test]
+        fn not_approved_transfer_should_fail() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Alice owns 1 token.
+            assert_eq!(erc721.balance_of(accounts.alice), 1);
+            // Bob does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.bob), 0);
+            // Eve does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.eve), 0);
+            // Set Eve as caller
+            set_caller(accounts.eve);
+            // Eve is not an approved operator by Alice.
+            assert_eq!(
+                erc721.transfer_from(accounts.alice, accounts.frank, 1),
+                Err(Error::NotApproved)
+            );
+            // Alice owns 1 token.
+            assert_eq!(erc721.balance_of(accounts.alice), 1);
+            // Bob does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.bob), 0);
+            // Eve does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.eve), 0);
+        }
+
+        #[ink::test]
+        fn burn_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Alice
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Alice owns 1 token.
+            assert_eq!(erc721.balance_of(accounts.alice), 1);
+            // Alice owns token Id 1.
+            assert_eq!(erc721.owner_of(1), Some(accounts.alice));
+            // Destroy token Id 1.
+            assert_eq!(erc721.burn(1), Ok(()));
+            // Alice does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.alice), 0);
+            // Token Id 1 does not exist
+            assert_eq!(erc721.owner_of(1), None);
+        }
+
+        #[ink::test]
+        fn burn_fails_token_not_found() {
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Try burning a non-existent token
+            assert_eq!(erc721.burn(1), Err(Error::TokenNotFound));
+        }
+
+        #[ink::test]
+        fn burn_fails_not_owner() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Alice
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Try burning this token with a different account
+            set_caller(accounts.eve);
+            assert_eq!(erc721.burn(1), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn transfer_from_fails_not_owner() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Alice
+            let token_uri1 = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri1), Ok(()));
+            // Bob can transfer Alice's tokens
+            assert_eq!(erc721.set_approval_for_all(accounts.bob, true), Ok(()));
+            // Frank can mint tokens
+            assert_eq!(erc721.grant_role(Role::Minter, accounts.frank), Ok(()));
+            // Set caller to Frank
+            set_caller(accounts.frank);
+            // Create token Id 2 for Frank
+            let token_uri2 = String::from("https://example.com/nft/2");
+            assert_eq!(erc721.mint(2, token_uri2), Ok(()));
+            // Set caller to Bob
+            set_caller(accounts.bob);
+            // Bob makes an invalid call to transfer_from (Alice is the token owner, not Frank)
+            assert_eq!(
+                erc721.transfer_from(accounts.frank, accounts.bob, 1),
+                Err(Error::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_fails_not_owner() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Alice
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Bob can transfer Alice's tokens
+            assert_eq!(erc721.set_approval_for_all(accounts.bob, true), Ok(()));
+            // Set caller to Bob
+            set_caller(accounts.bob);
+            // Bob makes an invalid call to transfer (he is not the token owner, Alice is)
+            assert_eq!(erc721.transfer(accounts.bob, 1), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn mint_fails_without_minter_role() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Set Bob as caller
+            set_caller(accounts.bob);
+            // Bob is not a minter.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Err(Error::MissingRole));
+            // Token Id 1 does not exist.
+            assert_eq!(erc721.owner_of(1), None);
+        }
+
+        #[ink::test]
+        fn grant_and_revoke_role_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Alice is granted all roles on deployment.
+            assert!(erc721.has_role(Role::Admin, accounts.alice));
+            assert!(erc721.has_role(Role::Minter, accounts.alice));
+            // Alice grants the minter role to Bob.
+            assert_eq!(erc721.grant_role(Role::Minter, accounts.bob), Ok(()));
+            assert!(erc721.has_role(Role::Minter, accounts.bob));
+            // Bob can mint.
+            set_caller(accounts.bob);
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri.clone()), Ok(()));
+            // Bob is not an admin and cannot grant roles.
+            assert_eq!(
+                erc721.grant_role(Role::Minter, accounts.eve),
+                Err(Error::MissingRole)
+            );
+            // Alice revokes the minter role from Bob.
+            set_caller(accounts.alice);
+            assert_eq!(erc721.revoke_role(Role::Minter, accounts.bob), Ok(()));
+            assert!(!erc721.has_role(Role::Minter, accounts.bob));
+            // Bob can no longer mint.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.mint(2, token_uri), Err(Error::MissingRole));
+        }
+
+        #[ink::test]
+        fn renounce_role_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Alice gives up the minter role.
+            assert_eq!(erc721.renounce_role(Role::Minter), Ok(()));
+            assert!(!erc721.has_role(Role::Minter, accounts.alice));
+            // Alice cannot renounce a role that is not held.
+            assert_eq!(erc721.renounce_role(Role::Minter), Err(Error::MissingRole));
+        }
+
+        #[ink::test]
+        fn burner_can_burn_any_token() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Grant the minter role to Bob and the burner role to Eve.
+            assert_eq!(erc721.grant_role(Role::Minter, accounts.bob), Ok(()));
+            assert_eq!(erc721.grant_role(Role::Burner, accounts.eve), Ok(()));
+            // Create token Id 1 for Bob
+            set_caller(accounts.bob);
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Eve burns Bob's token.
+            set_caller(accounts.eve);
+            assert_eq!(erc721.burn(1), Ok(()));
+            // Bob does not own tokens.
+            assert_eq!(erc721.balance_of(accounts.bob), 0);
+            // Token Id 1 does not exist
+            assert_eq!(erc721.owner_of(1), None);
+        }
+
+        #[ink::test]
+        fn enumeration_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Ids 1, 2 and 3 for Alice.
+            for id in 1..=3 {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint(id, token_uri), Ok(()));
+            }
+            // Three tokens exist.
+            assert_eq!(erc721.total_supply(), 3);
+            assert_eq!(erc721.token_by_index(0), Some(1));
+            assert_eq!(erc721.token_by_index(2), Some(3));
+            assert_eq!(erc721.token_by_index(3), None);
+            assert_eq!(erc721.token_of_owner_by_index(accounts.alice, 1), Some(2));
+            // Alice transfers token 1 to Bob.
+            assert_eq!(erc721.transfer(accounts.bob, 1), Ok(()));
+            // Alice's last token takes the slot of the transferred one.
+            assert_eq!(erc721.token_of_owner_by_index(accounts.alice, 0), Some(3));
+            assert_eq!(erc721.token_of_owner_by_index(accounts.alice, 1), Some(2));
+            assert_eq!(erc721.token_of_owner_by_index(accounts.alice, 2), None);
+            assert_eq!(erc721.token_of_owner_by_index(accounts.bob, 0), Some(1));
+            // Destroy token Id 1.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.burn(1), Ok(()));
+            // The last token takes the slot of the burned one.
+            assert_eq!(erc721.total_supply(), 2);
+            assert_eq!(erc721.token_by_index(0), Some(3));
+            assert_eq!(erc721.token_by_index(1), Some(2));
+            assert_eq!(erc721.token_by_index(2), None);
+            assert_eq!(erc721.token_of_owner_by_index(accounts.bob, 0), None);
+        }
+
+        #[ink::test]
+        fn bulk_queries_work() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Ids 1 to 5 for Alice.
+            for id in 1..=5 {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint(id, token_uri), Ok(()));
+            }
+            // Alice transfers token 5 to Bob.
+            assert_eq!(erc721.transfer(accounts.bob, 5), Ok(()));
+            // Owners and URIs are returned in the requested order.
+            assert_eq!(
+                erc721.owners_of(vec![5, 1, 6]),
+                Ok(vec![Some(accounts.bob), Some(accounts.alice), None])
+            );
+            assert_eq!(
+                erc721.token_uris_of(vec![2, 6]),
+                Ok(vec![Some(String::from("https://example.com/nft/2")), None])
+            );
+            // Alice's tokens are returned page by page.
+            assert_eq!(erc721.tokens_of_owner(accounts.alice, 0, 3), vec![1, 2, 3]);
+            assert_eq!(erc721.tokens_of_owner(accounts.alice, 3, 3), vec![4]);
+            assert!(erc721.tokens_of_owner(accounts.alice, 4, 3).is_empty());
+            assert_eq!(erc721.tokens_of_owner(accounts.bob, 0, u32::MAX), vec![5]);
+        }
+
+        #[ink::test]
+        fn bulk_query_too_large_should_fail() {
+            // Create a new contract instance.
+            let erc721 = Erc721::new();
+            // Too many tokens cannot be queried at once.
+            let ids = (0..=MAX_QUERY_SIZE).collect::<Vec<_>>();
+            assert_eq!(erc721.owners_of(ids.clone()), Err(Error::QueryTooLarge));
+            assert_eq!(erc721.token_uris_of(ids), Err(Error::QueryTooLarge));
+        }
+
+        #[ink::test]
+        fn royalty_info_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 without and token Id 2 with its own royalty.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            let token_uri = String::from("https://example.com/nft/2");
+            assert_eq!(
+                erc721.mint_with_royalty(
+                    accounts.alice,
+                    2,
+                    token_uri,
+                    accounts.eve,
+                    1_000,
+                ),
+                Ok(())
+            );
+            // No royalty applies to token Id 1 yet.
+            assert_eq!(erc721.royalty_info(1, 1_000), (AccountId::from([0x0; 32]), 0));
+            // Set a default royalty of 2.5% for Bob.
+            assert_eq!(erc721.set_default_royalty(accounts.bob, 250), Ok(()));
+            assert_eq!(erc721.royalty_info(1, 1_000), (accounts.bob, 25));
+            // Token Id 2 pays its own royalty of 10% to Eve.
+            assert_eq!(erc721.royalty_info(2, 1_000), (accounts.eve, 100));
+            assert_eq!(
+                erc721.royalty_info(2, Balance::MAX),
+                (accounts.eve, Balance::MAX / 10)
+            );
+            // Resetting the token royalty falls back to the default royalty.
+            assert_eq!(erc721.reset_token_royalty(2), Ok(()));
+            assert_eq!(erc721.royalty_info(2, 1_000), (accounts.bob, 25));
+            // Burning a token removes its royalty.
+            assert_eq!(erc721.set_token_royalty(1, accounts.eve, 500), Ok(()));
+            assert_eq!(erc721.burn(1), Ok(()));
+            assert_eq!(erc721.token_royalties.get(1), None);
+        }
+
+        #[ink::test]
+        fn set_royalty_fails() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Royalties cannot exceed the sale price.
+            assert_eq!(
+                erc721.set_default_royalty(accounts.bob, BASIS_POINTS + 1),
+                Err(Error::InvalidRoyalty)
+            );
+            // Royalties cannot be set for nonexistent tokens.
+            assert_eq!(
+                erc721.set_token_royalty(1, accounts.bob, 100),
+                Err(Error::TokenNotFound)
+            );
+            // Bob is not an admin and cannot set royalties.
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc721.set_default_royalty(accounts.bob, 100),
+                Err(Error::MissingRole)
+            );
+        }
+
+        #[ink::test]
+        fn safe_transfer_to_account_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Alice
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Bob is not a contract, so no receiver hook is called.
+            assert_eq!(
+                erc721.safe_transfer_from(accounts.alice, accounts.bob, 1, vec![]),
+                Ok(())
+            );
+            // Bob owns token 1
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+        }
+
+        #[ink::test]
+        fn pause_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Alice
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Pause transfers only.
+            assert_eq!(erc721.pause(Operation::Transfer), Ok(()));
+            assert!(erc721.is_paused(Operation::Transfer));
+            assert!(!erc721.is_paused(Operation::Mint));
+            assert_eq!(erc721.transfer(accounts.bob, 1), Err(Error::Paused));
+            // Minting is still possible.
+            let token_uri = String::from("https://example.com/nft/2");
+            assert_eq!(erc721.mint(2, token_uri), Ok(()));
+            // Unpause transfers.
+            assert_eq!(erc721.unpause(Operation::Transfer), Ok(()));
+            assert_eq!(erc721.transfer(accounts.bob, 1), Ok(()));
+            // Pause everything.
+            assert_eq!(erc721.pause(Operation::All), Ok(()));
+            let token_uri = String::from("https://example.com/nft/3");
+            assert_eq!(erc721.mint(3, token_uri), Err(Error::Paused));
+            assert_eq!(erc721.approve(accounts.bob, 2), Err(Error::Paused));
+            assert_eq!(
+                erc721.set_approval_for_all(accounts.bob, true),
+                Err(Error::Paused)
+            );
+            assert_eq!(erc721.burn(2), Err(Error::Paused));
+            // Alice still owns token 2.
+            assert_eq!(erc721.owner_of(2), Some(accounts.alice));
+        }
+
+        #[ink::test]
+        fn pause_fails_not_admin() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Bob is not an admin and cannot pause.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.pause(Operation::All), Err(Error::MissingRole));
+            assert!(!erc721.is_paused(Operation::Mint));
+        }
+
+        #[ink::test]
+        fn migrate_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // New instances start at the current storage version.
+            assert_eq!(erc721.storage_version(), STORAGE_VERSION);
+            assert_eq!(erc721.migrate(), Ok(()));
+            // Storage left behind by an older version is migrated.
+            erc721.storage_version = 0;
+            assert_eq!(erc721.migrate(), Ok(()));
+            assert_eq!(erc721.storage_version(), STORAGE_VERSION);
+            assert_eq!(5, ink::env::test::recorded_events().count());
+            // Storage written by a newer version cannot be migrated.
+            erc721.storage_version = STORAGE_VERSION + 1;
+            assert_eq!(erc721.migrate(), Err(Error::InvalidStorageVersion));
+            // Bob is not an admin and can neither migrate nor upgrade.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.migrate(), Err(Error::MissingRole));
+            assert_eq!(erc721.upgrade(Hash::from([0x1; 32])), Err(Error::MissingRole));
+        }
+
+        #[ink::test]
+        fn storage_root_keeps_version_1_layout() {
+            // Create a new contract instance with state added after version 1.
+            let mut erc721 = Erc721::with_max_supply(10);
+            let base_uri = String::from("https://example.com/nft/");
+            assert_eq!(erc721.set_base_uri(base_uri), Ok(()));
+            // The packed root holds the fields of version 1 only.
+            let mut root = Vec::new();
+            ink::storage::traits::Storable::encode(&erc721, &mut root);
+            let version_1 = (
+                erc721.all_tokens_count,
+                erc721.default_royalty,
+                erc721.storage_version,
+            );
+            assert_eq!(root, version_1.encode());
+        }
+
+        #[ink::test]
+        fn soulbound_transfer_should_fail() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create soulbound token Id 1 for Bob.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(
+                erc721.mint_soulbound(accounts.bob, 1, token_uri, BurnAuth::Both),
+                Ok(())
+            );
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert!(erc721.locked(1));
+            // Soulbound tokens can neither be transferred nor approved.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.transfer(accounts.charlie, 1), Err(Error::Soulbound));
+            assert_eq!(erc721.approve(accounts.charlie, 1), Err(Error::Soulbound));
+            assert_eq!(erc721.set_approval_for_all(accounts.charlie, true), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(
+                erc721.transfer_from(accounts.bob, accounts.charlie, 1),
+                Err(Error::Soulbound)
+            );
+            // Bob still owns token 1.
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+        }
+
+        #[ink::test]
+        fn soulbound_by_default_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Make all new tokens soulbound.
+            assert_eq!(erc721.set_soulbound_by_default(true), Ok(()));
+            // Create token Id 1 for Alice.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint(1, token_uri), Ok(()));
+            // Token 1 is soulbound.
+            assert!(erc721.locked(1));
+            assert_eq!(erc721.transfer(accounts.bob, 1), Err(Error::Soulbound));
+        }
+
+        #[ink::test]
+        fn burn_auth_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Bob is the only burner, Alice owns the tokens.
+            assert_eq!(erc721.grant_role(Role::Burner, accounts.bob), Ok(()));
+            assert_eq!(erc721.renounce_role(Role::Burner), Ok(()));
+            let auths = [BurnAuth::IssuerOnly, BurnAuth::OwnerOnly, BurnAuth::Neither];
+            for (id, auth) in (1..).zip(auths) {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(
+                    erc721.mint_soulbound(accounts.alice, id, token_uri, auth),
+                    Ok(())
+                );
+                assert_eq!(erc721.burn_auth(id), auth);
+            }
+            // Only the issuer can burn token 1.
+            assert_eq!(erc721.burn(1), Err(Error::NotAllowed));
+            set_caller(accounts.bob);
+            assert_eq!(erc721.burn(1), Ok(()));
+            // Only the owner can burn token 2.
+            assert_eq!(erc721.burn(2), Err(Error::NotOwner));
+            set_caller(accounts.alice);
+            assert_eq!(erc721.burn(2), Ok(()));
+            // Nobody can burn token 3.
+            assert_eq!(erc721.burn(3), Err(Error::NotAllowed));
+            set_caller(accounts.bob);
+            assert_eq!(erc721.burn(3), Err(Error::NotOwner));
+            assert_eq!(erc721.owner_of(3), Some(accounts.alice));
+        }
+
+        #[ink::test]
+        fn mint_to_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Bob.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.bob, 1, token_uri), Ok(()));
+            // Bob owns token 1.
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(erc721.balance_of(accounts.alice), 0);
+            // A Transfer event from nobody of each standard follows the four role
+            // grants.
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(6, events.len());
+            let transfer =
+                <Transfer as ink::scale::Decode>::decode(&mut &events[4].data[..])
+                    .expect("encountered invalid event data");
+            assert_eq!(transfer.from, None);
+            assert_eq!(transfer.to, Some(accounts.bob));
+            let transfer =
+                <psp34::Transfer as ink::scale::Decode>::decode(&mut &events[5].data[..])
+                    .expect("encountered invalid event data");
+            assert_eq!(transfer.from, None);
+            assert_eq!(transfer.to, Some(accounts.bob));
+            assert_eq!(transfer.id, Id::U32(1));
+        }
+
+        #[ink::test]
+        fn mint_next_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Token IDs are assigned in order.
+            let token_uri = String::from("https://example.com/nft");
+            assert_eq!(erc721.mint_next(accounts.bob, token_uri.clone()), Ok(1));
+            // Create token Id 2 explicitly.
+            assert_eq!(erc721.mint(2, token_uri.clone()), Ok(()));
+            // Token Id 2 is skipped.
+            assert_eq!(erc721.mint_next(accounts.eve, token_uri), Ok(3));
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(erc721.owner_of(2), Some(accounts.alice));
+            assert_eq!(erc721.owner_of(3), Some(accounts.eve));
+        }
+
+        #[ink::test]
+        fn max_supply_works() {
+            // Create a new contract instance capped at two tokens.
+            let mut erc721 = Erc721::with_max_supply(2);
+            assert_eq!(erc721.max_supply(), Some(2));
+            // Create token Ids 1 and 2.
+            let token_uri = String::from("https://example.com/nft");
+            assert_eq!(erc721.mint(1, token_uri.clone()), Ok(()));
+            assert_eq!(erc721.mint(2, token_uri.clone()), Ok(()));
+            // The supply is exhausted.
+            assert_eq!(erc721.mint(3, token_uri.clone()), Err(Error::SupplyExceeded));
+            // Burning a token does not free up supply.
+            assert_eq!(erc721.burn(1), Ok(()));
+            assert_eq!(erc721.total_minted(), 2);
+            assert_eq!(erc721.total_burned(), 1);
+            assert_eq!(erc721.total_supply(), 1);
+            assert_eq!(erc721.mint(3, token_uri), Err(Error::SupplyExceeded));
+        }
+
+        #[ink::test]
+        fn mint_limit_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Each account can receive one minted token.
+            assert_eq!(erc721.set_mint_limit(Some(1)), Ok(()));
+            let token_uri = String::from("https://example.com/nft");
+            assert_eq!(erc721.mint_to(accounts.bob, 1, token_uri.clone()), Ok(()));
+            assert_eq!(erc721.minted_count(accounts.bob), 1);
+            assert_eq!(
+                erc721.mint_to(accounts.bob, 2, token_uri.clone()),
+                Err(Error::MintLimitReached)
+            );
+            // Other accounts can still receive tokens.
+            assert_eq!(erc721.mint_to(accounts.eve, 2, token_uri.clone()), Ok(()));
+            // Removing the limit allows minting to Bob again.
+            assert_eq!(erc721.set_mint_limit(None), Ok(()));
+            assert_eq!(erc721.mint_to(accounts.bob, 3, token_uri), Ok(()));
+        }
+
+        #[ink::test]
+        fn mint_window_works() {
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Minting is open from 100 until 200.
+            assert_eq!(erc721.set_mint_window(Some(100), Some(200)), Ok(()));
+            let token_uri = String::from("https://example.com/nft");
+            set_block_timestamp(99);
+            assert_eq!(erc721.mint(1, token_uri.clone()), Err(Error::MintClosed));
+            set_block_timestamp(100);
+            assert_eq!(erc721.mint(1, token_uri.clone()), Ok(()));
+            set_block_timestamp(200);
+            assert_eq!(erc721.mint(2, token_uri), Err(Error::MintClosed));
+        }
+
+        #[ink::test]
+        fn mint_with_voucher_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Register the voucher signer.
+            let signer = sr25519_keypair();
+            assert_eq!(
+                erc721.set_voucher_signer(Some(signer.public.to_bytes())),
+                Ok(())
+            );
+            // Sign a voucher for token Id 1 and Bob.
+            let voucher = MintVoucher {
+                recipient: accounts.bob,
+                id: 1,
+                uri: String::from("https://example.com/nft/1"),
+                expiry: 100,
+                nonce: 7,
+            };
+            let signature = sr25519_sign(&signer, &erc721.voucher_payload(&voucher));
+            // Eve redeems the voucher at its expiry, which is still valid.
+            set_caller(accounts.eve);
+            set_block_timestamp(100);
+            assert_eq!(erc721.mint_with_voucher(voucher.clone(), signature), Ok(()));
+            // Bob owns token 1.
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert!(erc721.is_voucher_nonce_used(7));
+            // The voucher cannot be redeemed twice.
+            assert_eq!(
+                erc721.mint_with_voucher(voucher, signature),
+                Err(Error::NonceAlreadyUsed)
+            );
+        }
+
+        #[ink::test]
+        fn mint_with_invalid_voucher_should_fail() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            let signer = sr25519_keypair();
+            let mut voucher = MintVoucher {
+                recipient: accounts.bob,
+                id: 1,
+                uri: String::from("https://example.com/nft/1"),
+                expiry: 100,
+                nonce: 7,
+            };
+            let signature = sr25519_sign(&signer, &erc721.voucher_payload(&voucher));
+            // Vouchers are rejected while no signer is registered.
+            assert_eq!(
+                erc721.mint_with_voucher(voucher.clone(), signature),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(
+                erc721.set_voucher_signer(Some(signer.public.to_bytes())),
+                Ok(())
+            );
+            // Vouchers are rejected once expired.
+            set_block_timestamp(101);
+            assert_eq!(
+                erc721.mint_with_voucher(voucher.clone(), signature),
+                Err(Error::SignatureExpired)
+            );
+            // Vouchers signed without the domain tag are rejected.
+            set_block_timestamp(0);
+            let untagged = sr25519_sign(&signer, &erc721.signed_payload(&voucher));
+            assert_eq!(
+                erc721.mint_with_voucher(voucher.clone(), untagged),
+                Err(Error::InvalidSignature)
+            );
+            // Tampered vouchers are rejected.
+            voucher.recipient = accounts.eve;
+            assert_eq!(
+                erc721.mint_with_voucher(voucher, signature),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(erc721.owner_of(1), None);
+        }
+
+        #[ink::test]
+        fn permit_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for an account controlled by an sr25519 key.
+            let keypair = sr25519_keypair();
+            let owner = AccountId::from(keypair.public.to_bytes());
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(owner, 1, token_uri), Ok(()));
+            // The owner permits Eve to transfer token 1.
+            let payload = erc721.permit_payload(accounts.eve, 1, 100);
+            let signature = sr25519_sign(&keypair, &payload);
+            // Bob submits the permit at its deadline, which is still valid.
+            set_caller(accounts.bob);
+            set_block_timestamp(100);
+            assert_eq!(erc721.permit(accounts.eve, 1, 100, signature), Ok(()));
+            assert_eq!(erc721.get_approved(1), Some(accounts.eve));
+            assert_eq!(erc721.permit_nonce(1), 1);
+            // The permit cannot be replayed.
+            assert_eq!(
+                erc721.permit(accounts.eve, 1, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+            // Eve takes over token 1.
+            set_caller(accounts.eve);
+            assert_eq!(erc721.transfer_from(owner, accounts.eve, 1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.eve));
+        }
+
+        #[ink::test]
+        fn permit_fails() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            let keypair = sr25519_keypair();
+            let owner = AccountId::from(keypair.public.to_bytes());
+            // Permits for nonexistent tokens are rejected.
+            let payload = erc721.permit_payload(accounts.eve, 1, 100);
+            let signature = sr25519_sign(&keypair, &payload);
+            assert_eq!(
+                erc721.permit(accounts.eve, 1, 100, signature),
+                Err(Error::TokenNotFound)
+            );
+            // Create token Id 1 for the key owner.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(owner, 1, token_uri), Ok(()));
+            // Permits for another spender are rejected.
+            assert_eq!(
+                erc721.permit(accounts.bob, 1, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+            // Permits past their deadline are rejected.
+            set_block_timestamp(101);
+            assert_eq!(
+                erc721.permit(accounts.eve, 1, 100, signature),
+                Err(Error::SignatureExpired)
+            );
+            assert_eq!(erc721.get_approved(1), None);
+        }
+
+        #[ink::test]
+        fn mint_with_ecdsa_voucher_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            let secret_key = ecdsa_secret_key();
+            let issuer = eth_address(&secret_key);
+            // Sign a voucher for token Id 1 and Bob.
+            let voucher = MintVoucher {
+                recipient: accounts.bob,
+                id: 1,
+                uri: String::from("https://example.com/nft/1"),
+                expiry: 100,
+                nonce: 7,
+            };
+            let payload = erc721.voucher_payload(&voucher);
+            let signature = ecdsa_sign(&erc721, &secret_key, &payload);
+            // The issuer is not a minter yet.
+            assert_eq!(
+                erc721.mint_with_ecdsa_voucher(voucher.clone(), signature),
+                Err(Error::MissingRole)
+            );
+            // Register the issuer as a minter.
+            assert_eq!(erc721.grant_eth_role(Role::Minter, issuer), Ok(()));
+            assert!(erc721.has_eth_role(Role::Minter, issuer));
+            // Eve cannot redeem the voucher after its expiry, but can at its expiry.
+            set_caller(accounts.eve);
+            set_block_timestamp(101);
+            assert_eq!(
+                erc721.mint_with_ecdsa_voucher(voucher.clone(), signature),
+                Err(Error::SignatureExpired)
+            );
+            set_block_timestamp(100);
+            assert_eq!(
+                erc721.mint_with_ecdsa_voucher(voucher.clone(), signature),
+                Ok(())
+            );
+            // Bob owns token 1.
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            // The voucher cannot be redeemed twice.
+            assert_eq!(
+                erc721.mint_with_ecdsa_voucher(voucher, signature),
+                Err(Error::NonceAlreadyUsed)
+            );
+            // Only an admin can revoke the role.
+            assert_eq!(
+                erc721.revoke_eth_role(Role::Minter, issuer),
+                Err(Error::MissingRole)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc721.revoke_eth_role(Role::Minter, issuer), Ok(()));
+            assert!(!erc721.has_eth_role(Role::Minter, issuer));
+        }
+
+        #[ink::test]
+        fn permit_ecdsa_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
             // Create a new contract instance.
             let mut erc721 = Erc721::new();
-            // Create token Id 1 for Alice
+            // Create token Id 1 for an account controlled by a secp256k1 key.
+            let secret_key = ecdsa_secret_key();
+            let public_key = secret_key.public_key(secp256k1::SECP256K1).serialize();
+            let mut owner = [0x0; 32];
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut owner);
+            let owner = AccountId::from(owner);
             let token_uri = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri), Ok(()));
-            // Alice owns token 1
-            assert_eq!(erc721.balance_of(accounts.alice), 1);
-            // Bob does not own any token
-            assert_eq!(erc721.balance_of(accounts.bob), 0);
-            // The first Transfer event takes place
-            assert_eq!(1, ink::env::test::recorded_events().count());
-            // Alice transfers token 1 to Bob
-            assert_eq!(erc721.transfer(accounts.bob, 1), Ok(()));
-            // The second Transfer event takes place
-            assert_eq!(2, ink::env::test::recorded_events().count());
-            // Bob owns token 1
-            assert_eq!(erc721.balance_of(accounts.bob), 1);
+            assert_eq!(erc721.mint_to(owner, 1, token_uri), Ok(()));
+            // The owner permits Eve to transfer token 1.
+            let payload = erc721.permit_payload(accounts.eve, 1, 100);
+            let signature = ecdsa_sign(&erc721, &secret_key, &payload);
+            // Permits for another spender are rejected.
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc721.permit_ecdsa(accounts.bob, 1, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+            // Permits past their deadline are rejected.
+            set_block_timestamp(101);
+            assert_eq!(
+                erc721.permit_ecdsa(accounts.eve, 1, 100, signature),
+                Err(Error::SignatureExpired)
+            );
+            // Bob submits the permit at its deadline, which is still valid.
+            set_block_timestamp(100);
+            assert_eq!(erc721.permit_ecdsa(accounts.eve, 1, 100, signature), Ok(()));
+            assert_eq!(erc721.get_approved(1), Some(accounts.eve));
+            assert_eq!(erc721.permit_nonce(1), 1);
         }
 
         #[ink::test]
-        fn invalid_transfer_should_fail() {
+        fn execute_signed_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
             // Create a new contract instance.
             let mut erc721 = Erc721::new();
-            // Transfer token fails if it does not exist.
-            assert_eq!(erc721.transfer(accounts.bob, 2), Err(Error::TokenNotFound));
-            // Token Id 2 does not exist.
-            assert_eq!(erc721.owner_of(2), None);
-            // Create token Id 2.
+            // Create token Ids 1 and 2 for an account controlled by an sr25519 key.
+            let keypair = sr25519_keypair();
+            let signer = AccountId::from(keypair.public.to_bytes());
+            for id in 1..=2 {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint_to(signer, id, token_uri), Ok(()));
+            }
+            // Bob relays signed operations of the key owner.
+            set_caller(accounts.bob);
+            let operations = [
+                SignedOperation::Approve {
+                    to: accounts.eve,
+                    id: 1,
+                },
+                SignedOperation::SetApprovalForAll {
+                    operator: accounts.frank,
+                    approved: true,
+                },
+                SignedOperation::Transfer {
+                    from: signer,
+                    to: accounts.charlie,
+                    id: 2,
+                },
+                SignedOperation::Burn { id: 1 },
+            ];
+            for (nonce, operation) in (0..).zip(operations) {
+                let payload = erc721.signed_operation_payload(&operation, nonce, 0);
+                let signature = sr25519_sign(&keypair, &payload);
+                assert_eq!(
+                    erc721.execute_signed(operation, signer, signature, nonce, 0),
+                    Ok(())
+                );
+                if nonce == 0 {
+                    assert_eq!(erc721.get_approved(1), Some(accounts.eve));
+                }
+            }
+            assert!(erc721.is_approved_for_all(signer, accounts.frank));
+            assert_eq!(erc721.owner_of(2), Some(accounts.charlie));
+            assert_eq!(erc721.owner_of(1), None);
+            assert_eq!(erc721.signed_operation_nonce(signer), 4);
+        }
+
+        #[ink::test]
+        fn execute_signed_pays_relayer_fee() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for an account controlled by an sr25519 key.
+            let keypair = sr25519_keypair();
+            let signer = AccountId::from(keypair.public.to_bytes());
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(signer, 1, token_uri), Ok(()));
+            // The key owner deposits 100 to pay relayer fees.
+            set_caller(signer);
+            set_balance(signer, 1_000_000);
+            transfer_in(100);
+            assert_eq!(erc721.deposit_fees(), Ok(()));
+            assert_eq!(erc721.fee_deposit(signer), 100);
+            // The key owner signs a transfer paying a fee of 60.
+            let operation = SignedOperation::Transfer {
+                from: signer,
+                to: accounts.eve,
+                id: 1,
+            };
+            let payload = erc721.signed_operation_payload(&operation, 0, 60);
+            let signature = sr25519_sign(&keypair, &payload);
+            // Operations cannot be relayed with a wrong nonce or fee.
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc721.execute_signed(operation.clone(), signer, signature, 1, 60),
+                Err(Error::InvalidNonce)
+            );
+            assert_eq!(
+                erc721.execute_signed(operation.clone(), signer, signature, 0, 100),
+                Err(Error::InvalidSignature)
+            );
+            // Bob relays the transfer and receives the fee.
+            let relayer_balance = get_balance(accounts.bob);
+            assert_eq!(
+                erc721.execute_signed(operation.clone(), signer, signature, 0, 60),
+                Ok(())
+            );
+            assert_eq!(erc721.owner_of(1), Some(accounts.eve));
+            assert_eq!(get_balance(accounts.bob), relayer_balance + 60);
+            assert_eq!(erc721.fee_deposit(signer), 40);
+            // The operation cannot be replayed.
+            assert_eq!(
+                erc721.execute_signed(operation, signer, signature, 0, 60),
+                Err(Error::InvalidNonce)
+            );
+            // The key owner withdraws the rest of the deposit.
+            set_caller(signer);
+            assert_eq!(erc721.withdraw_fees(50), Err(Error::InsufficientDeposit));
+            assert_eq!(erc721.withdraw_fees(40), Ok(()));
+            assert_eq!(get_balance(signer), 1_000_000 - 100 + 40);
+        }
+
+        #[ink::test]
+        fn buy_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Eve receives a royalty of 10% and Frank a marketplace fee of 5%.
+            assert_eq!(erc721.set_default_royalty(accounts.eve, 1_000), Ok(()));
+            assert_eq!(
+                erc721.set_marketplace_fee(Some(accounts.frank), 500),
+                Ok(())
+            );
+            // Create token Id 1 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Charlie lists token 1 for 1000.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.list(1, 1_000), Ok(()));
+            assert_eq!(
+                erc721.listing(1),
+                Some(Listing {
+                    seller: accounts.charlie,
+                    price: 1_000,
+                })
+            );
+            // Bob cannot buy token 1 for less.
+            set_caller(accounts.bob);
+            set_value_transferred(999);
+            assert_eq!(erc721.buy(1), Err(Error::IncorrectPayment));
+            // Bob buys token 1.
+            let charlie_balance = get_balance(accounts.charlie);
+            transfer_in(1_000);
+            assert_eq!(erc721.buy(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(erc721.listing(1), None);
+            // The price is split between Eve, Frank and Charlie.
+            assert_eq!(get_balance(accounts.eve), 100);
+            assert_eq!(get_balance(accounts.frank), 50);
+            assert_eq!(get_balance(accounts.charlie), charlie_balance + 850);
+        }
+
+        #[ink::test]
+        fn listing_is_invalidated() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Ids 1 and 2 for Charlie.
+            set_caller(accounts.alice);
+            for id in 1..=2 {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint_to(accounts.charlie, id, token_uri), Ok(()));
+            }
+            // Only the owner can list a token.
+            assert_eq!(erc721.list(1, 100), Err(Error::NotOwner));
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.list(1, 100), Ok(()));
+            assert_eq!(erc721.list(2, 100), Ok(()));
+            // Only the seller can cancel a listing.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.cancel_listing(1), Err(Error::NotOwner));
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.cancel_listing(1), Ok(()));
+            assert_eq!(erc721.listing(1), None);
+            // Transferring a listed token invalidates its listing.
+            assert_eq!(erc721.list(1, 100), Ok(()));
+            assert_eq!(erc721.transfer(accounts.eve, 1), Ok(()));
+            assert_eq!(erc721.listing(1), None);
+            // Burning a listed token invalidates its listing.
+            assert_eq!(erc721.burn(2), Ok(()));
+            assert_eq!(erc721.listing(2), None);
+            // Bob cannot buy tokens that are no longer listed.
+            set_caller(accounts.bob);
+            set_value_transferred(100);
+            assert_eq!(erc721.buy(1), Err(Error::NotListed));
+        }
+
+        #[ink::test]
+        fn listing_fails_when_paused() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Ids 1 and 2 for Charlie, who lists token 1.
+            for id in 1..=2 {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint_to(accounts.charlie, id, token_uri), Ok(()));
+            }
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.list(1, 100), Ok(()));
+            // Alice pauses all operations.
+            set_caller(accounts.alice);
+            assert_eq!(erc721.pause(Operation::All), Ok(()));
+            // No token can be listed or bought.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.list(2, 100), Err(Error::Paused));
+            set_caller(accounts.bob);
+            set_value_transferred(100);
+            assert_eq!(erc721.buy(1), Err(Error::Paused));
+            // Charlie can still cancel the listing.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.cancel_listing(1), Ok(()));
+            assert_eq!(erc721.listing(1), None);
+        }
+
+        #[ink::test]
+        fn english_auction_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            // Create token Id 1 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Charlie auctions token 1 with a reserve price of 100 for an hour.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.start_english_auction(1, 100, 3_600_000), Ok(()));
+            // The token is escrowed and Charlie can no longer move it.
+            assert_eq!(erc721.owner_of(1), Some(contract));
+            assert_eq!(erc721.transfer(accounts.bob, 1), Err(Error::NotApproved));
+            // Bob cannot bid below the reserve price.
+            set_caller(accounts.bob);
+            set_value_transferred(99);
+            assert_eq!(erc721.bid(1), Err(Error::BidTooLow));
+            // Bob bids 100 and Django outbids them.
+            set_balance(accounts.django, 1_000_000);
+            let bob_balance = get_balance(accounts.bob);
+            transfer_in(100);
+            assert_eq!(erc721.bid(1), Ok(()));
+            set_caller(accounts.django);
+            set_value_transferred(100);
+            assert_eq!(erc721.bid(1), Err(Error::BidTooLow));
+            transfer_in(150);
+            assert_eq!(erc721.bid(1), Ok(()));
+            // Bob is refunded.
+            assert_eq!(get_balance(accounts.bob), bob_balance);
+            // A late bid extends the auction.
+            set_block_timestamp(3_500_000);
+            set_caller(accounts.bob);
+            transfer_in(200);
+            assert_eq!(erc721.bid(1), Ok(()));
+            let end = erc721.auction(1).unwrap().end;
+            assert_eq!(end, 3_500_000 + AUCTION_EXTENSION);
+            // The auction cannot be settled before it ends.
+            assert_eq!(erc721.settle_auction(1), Err(Error::AuctionNotEnded));
+            // Anyone can settle the ended auction.
+            set_block_timestamp(end);
+            let charlie_balance = get_balance(accounts.charlie);
+            set_caller(accounts.eve);
+            assert_eq!(erc721.settle_auction(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(erc721.auction(1), None);
+            assert_eq!(get_balance(accounts.charlie), charlie_balance + 200);
+        }
+
+        #[ink::test]
+        fn auction_settles_after_fees_are_raised() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Charlie auctions token 1 and Bob bids 1000 on it.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.start_english_auction(1, 100, 3_600_000), Ok(()));
+            set_caller(accounts.bob);
+            transfer_in(1_000);
+            assert_eq!(erc721.bid(1), Ok(()));
+            // The admin raises the royalty to 60% and the fee to 50% after the bid.
+            set_caller(accounts.alice);
+            assert_eq!(erc721.set_default_royalty(accounts.eve, 6_000), Ok(()));
+            assert_eq!(
+                erc721.set_marketplace_fee(Some(accounts.frank), 5_000),
+                Ok(())
+            );
+            // The auction still settles, with the fee capped at the rest of the bid.
+            set_block_timestamp(3_600_000);
+            let charlie_balance = get_balance(accounts.charlie);
+            assert_eq!(erc721.settle_auction(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(get_balance(accounts.eve), 600);
+            assert_eq!(get_balance(accounts.frank), 400);
+            assert_eq!(get_balance(accounts.charlie), charlie_balance);
+        }
+
+        #[ink::test]
+        fn escrowed_token_cannot_be_burned() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Charlie auctions token 1 and Bob bids on it.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.start_english_auction(1, 100, 3_600_000), Ok(()));
+            set_caller(accounts.bob);
+            transfer_in(100);
+            assert_eq!(erc721.bid(1), Ok(()));
+            // Alice, a burner, cannot burn the escrowed token.
+            set_caller(accounts.alice);
+            assert_eq!(erc721.burn(1), Err(Error::Escrowed));
+            // The auction and the bid of Bob are left intact.
+            let highest_bid = erc721.auction(1).and_then(|auction| auction.highest_bid);
+            assert_eq!(
+                highest_bid,
+                Some(Bid {
+                    bidder: accounts.bob,
+                    amount: 100
+                })
+            );
+        }
+
+        #[ink::test]
+        fn auction_fails_when_paused() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Ids 1 and 2 for Charlie, who auctions token 1.
+            for id in 1..=2 {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint_to(accounts.charlie, id, token_uri), Ok(()));
+            }
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.start_english_auction(1, 100, 3_600_000), Ok(()));
+            // Alice pauses all operations.
+            set_caller(accounts.alice);
+            assert_eq!(erc721.pause(Operation::All), Ok(()));
+            // No auction can be started, bid on or settled.
+            set_caller(accounts.charlie);
+            assert_eq!(
+                erc721.start_english_auction(2, 100, 3_600_000),
+                Err(Error::Paused)
+            );
+            set_caller(accounts.bob);
+            set_value_transferred(100);
+            assert_eq!(erc721.bid(1), Err(Error::Paused));
+            set_block_timestamp(3_600_000);
+            assert_eq!(erc721.settle_auction(1), Err(Error::Paused));
+            assert!(erc721.auction(1).is_some());
+            // Charlie can still cancel the auction and get token 1 back.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.cancel_auction(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.charlie));
+        }
+
+        #[ink::test]
+        fn dutch_auction_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Ids 1 and 2 for Charlie.
+            for id in 1..=2 {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint_to(accounts.charlie, id, token_uri), Ok(()));
+            }
+            // Charlie auctions both tokens from 1000 down to 200 over 1000 ms.
+            set_caller(accounts.charlie);
+            assert_eq!(
+                erc721.start_dutch_auction(1, 200, 1_000, 1_000),
+                Err(Error::InvalidAuction)
+            );
+            assert_eq!(erc721.start_dutch_auction(1, 1_000, 200, 1_000), Ok(()));
+            assert_eq!(erc721.start_dutch_auction(2, 1_000, 200, 1_000), Ok(()));
+            // The price falls linearly.
+            set_block_timestamp(250);
+            assert_eq!(erc721.dutch_auction_price(1), Some(800));
+            // Bob overpays for token 1 and gets the change back.
+            set_caller(accounts.bob);
+            set_value_transferred(799);
+            assert_eq!(erc721.buy_at_auction(1), Err(Error::IncorrectPayment));
+            let bob_balance = get_balance(accounts.bob);
+            transfer_in(900);
+            assert_eq!(erc721.buy_at_auction(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(get_balance(accounts.bob), bob_balance - 800);
+            // Token 2 goes back to Charlie once its auction ends unsold.
+            set_block_timestamp(1_000);
+            assert_eq!(erc721.buy_at_auction(2), Err(Error::AuctionEnded));
+            assert_eq!(erc721.settle_auction(2), Ok(()));
+            assert_eq!(erc721.owner_of(2), Some(accounts.charlie));
+        }
+
+        #[ink::test]
+        fn offers_work() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Ids 1 and 2 for Charlie.
+            for id in 1..=2 {
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint_to(accounts.charlie, id, token_uri), Ok(()));
+            }
+            // Bob offers 300 for token 1 and 200 for any token.
+            set_caller(accounts.bob);
+            transfer_in(300);
+            assert_eq!(erc721.make_offer(1, 1_000), Ok(1));
+            transfer_in(200);
+            assert_eq!(erc721.make_collection_offer(1_000), Ok(2));
+            // Only Charlie can accept the offer for token 1.
+            assert_eq!(erc721.accept_offer(1), Err(Error::NotOwner));
+            set_caller(accounts.charlie);
+            let charlie_balance = get_balance(accounts.charlie);
+            assert_eq!(erc721.accept_offer(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(get_balance(accounts.charlie), charlie_balance + 300);
+            assert_eq!(erc721.offer(1), None);
+            // A collection offer is accepted with the token to sell.
+            assert_eq!(erc721.accept_offer(2), Err(Error::NoOffer));
+            assert_eq!(erc721.accept_collection_offer(2, 2), Ok(()));
+            assert_eq!(erc721.owner_of(2), Some(accounts.bob));
+            assert_eq!(get_balance(accounts.charlie), charlie_balance + 500);
+        }
+
+        #[ink::test]
+        fn offers_are_refunded() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Bob makes two offers for token 1.
+            set_caller(accounts.bob);
+            let bob_balance = get_balance(accounts.bob);
+            transfer_in(300);
+            assert_eq!(erc721.make_offer(1, 1_000), Ok(1));
+            transfer_in(200);
+            assert_eq!(erc721.make_offer(1, 1_000), Ok(2));
+            // Bob cancels the first offer and is refunded.
+            assert_eq!(erc721.cancel_offer(1), Ok(()));
+            assert_eq!(get_balance(accounts.bob), bob_balance - 200);
+            // Only Bob can cancel the second offer before it expires.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.cancel_offer(2), Err(Error::NotAllowed));
+            // Expired offers cannot be accepted, but anyone can refund them.
+            set_block_timestamp(1_000);
+            assert_eq!(erc721.accept_offer(2), Err(Error::OfferExpired));
+            assert_eq!(erc721.cancel_offer(2), Ok(()));
+            assert_eq!(get_balance(accounts.bob), bob_balance);
+            assert_eq!(erc721.cancel_offer(2), Err(Error::NoOffer));
+        }
+
+        #[ink::test]
+        fn offers_fail_when_paused() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Bob offers 300 for token 1.
+            set_caller(accounts.bob);
+            transfer_in(300);
+            assert_eq!(erc721.make_offer(1, 1_000), Ok(1));
+            // Alice pauses all operations.
+            set_caller(accounts.alice);
+            assert_eq!(erc721.pause(Operation::All), Ok(()));
+            // No offer can be made or accepted.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.make_offer(1, 1_000), Err(Error::Paused));
+            assert_eq!(erc721.make_collection_offer(1_000), Err(Error::Paused));
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.accept_offer(1), Err(Error::Paused));
+            assert_eq!(erc721.owner_of(1), Some(accounts.charlie));
+            assert!(erc721.offer(1).is_some());
+            // Bob can still cancel the offer and get refunded.
+            set_caller(accounts.bob);
+            let bob_balance = get_balance(accounts.bob);
+            assert_eq!(erc721.cancel_offer(1), Ok(()));
+            assert_eq!(erc721.offer(1), None);
+            assert_eq!(get_balance(accounts.bob), bob_balance + 300);
+        }
+
+        #[ink::test]
+        fn swap_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Ids 1 and 2 for Bob and token Id 3 for Charlie.
+            for id in 1..=3 {
+                let owner = if id < 3 { accounts.bob } else { accounts.charlie };
+                let token_uri = format!("https://example.com/nft/{id}");
+                assert_eq!(erc721.mint_to(owner, id, token_uri), Ok(()));
+            }
+            // Bob offers tokens 1 and 2 for token 3 and 500.
+            set_caller(accounts.bob);
+            let requested = [3].to_vec();
+            let duplicated = [1, 1].to_vec();
+            assert_eq!(
+                erc721.propose_swap(accounts.charlie, duplicated, requested.clone(), 500),
+                Err(Error::NotAllowed)
+            );
+            // Bob must first approve the contract to transfer the offered tokens.
+            let offered = [1, 2].to_vec();
+            assert_eq!(
+                erc721.propose_swap(accounts.charlie, offered, requested.clone(), 500),
+                Err(Error::NotApproved)
+            );
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc721.set_approval_for_all(contract, true), Ok(()));
+            assert_eq!(
+                erc721.propose_swap(accounts.charlie, [1, 2].to_vec(), requested, 500),
+                Ok(1)
+            );
+            // Only Charlie can accept the swap, paying the requested value.
+            assert_eq!(erc721.accept_swap(1), Err(Error::NotAllowed));
+            set_caller(accounts.charlie);
+            set_value_transferred(400);
+            assert_eq!(erc721.accept_swap(1), Err(Error::IncorrectPayment));
+            let bob_balance = get_balance(accounts.bob);
+            transfer_in(500);
+            assert_eq!(erc721.accept_swap(1), Ok(()));
+            // All tokens and value changed hands.
+            assert_eq!(erc721.owner_of(1), Some(accounts.charlie));
+            assert_eq!(erc721.owner_of(2), Some(accounts.charlie));
+            assert_eq!(erc721.owner_of(3), Some(accounts.bob));
+            assert_eq!(get_balance(accounts.bob), bob_balance + 500);
+            assert_eq!(erc721.swap(1), None);
+        }
+
+        #[ink::test]
+        fn swap_fails_after_ownership_changed() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Bob and token Id 2 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.bob, 1, token_uri), Ok(()));
             let token_uri = String::from("https://example.com/nft/2");
-            assert_eq!(erc721.mint(2, token_uri), Ok(()));
-            // Alice owns 1 token.
-            assert_eq!(erc721.balance_of(accounts.alice), 1);
-            // Token Id 2 is owned by Alice.
-            assert_eq!(erc721.owner_of(2), Some(accounts.alice));
-            // Set Bob as caller
+            assert_eq!(erc721.mint_to(accounts.charlie, 2, token_uri), Ok(()));
+            // Bob approves the contract and offers token 1 and 200 for token 2.
+            set_caller(accounts.bob);
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc721.set_approval_for_all(contract, true), Ok(()));
+            let bob_balance = get_balance(accounts.bob);
+            transfer_in(200);
+            assert_eq!(
+                erc721.propose_swap(accounts.charlie, [1].to_vec(), [2].to_vec(), 0),
+                Ok(1)
+            );
+            // Bob gives token 1 away before Charlie accepts.
+            assert_eq!(erc721.transfer(accounts.eve, 1), Ok(()));
+            set_caller(accounts.charlie);
+            set_value_transferred(0);
+            assert_eq!(erc721.accept_swap(1), Err(Error::NotOwner));
+            assert_eq!(erc721.owner_of(2), Some(accounts.charlie));
+            // Charlie rejects the swap and Bob is refunded.
+            assert_eq!(erc721.cancel_swap(1), Ok(()));
+            assert_eq!(get_balance(accounts.bob), bob_balance);
+        }
+
+        #[ink::test]
+        fn swap_fails_after_approval_revoked() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Bob and token Id 2 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.bob, 1, token_uri), Ok(()));
+            let token_uri = String::from("https://example.com/nft/2");
+            assert_eq!(erc721.mint_to(accounts.charlie, 2, token_uri), Ok(()));
+            // Bob approves the contract for all tokens and offers token 1 for token 2.
+            set_caller(accounts.bob);
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc721.set_approval_for_all(contract, true), Ok(()));
+            assert_eq!(
+                erc721.propose_swap(accounts.charlie, [1].to_vec(), [2].to_vec(), 0),
+                Ok(1)
+            );
+            // Bob revokes the approval.
+            assert_eq!(erc721.set_approval_for_all(contract, false), Ok(()));
+            // Charlie can no longer accept the swap and no token moves.
+            set_caller(accounts.charlie);
+            set_value_transferred(0);
+            assert_eq!(erc721.accept_swap(1), Err(Error::NotApproved));
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(erc721.owner_of(2), Some(accounts.charlie));
+            // Once Bob approves the contract for token 1, the swap goes through.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.approve(contract, 1), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.accept_swap(1), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.charlie));
+            assert_eq!(erc721.owner_of(2), Some(accounts.bob));
+        }
+
+        #[ink::test]
+        fn swap_fails_when_paused() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Bob and token Id 2 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.bob, 1, token_uri), Ok(()));
+            let token_uri = String::from("https://example.com/nft/2");
+            assert_eq!(erc721.mint_to(accounts.charlie, 2, token_uri), Ok(()));
+            // Bob approves the contract and offers token 1 and 200 for token 2.
+            set_caller(accounts.bob);
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc721.set_approval_for_all(contract, true), Ok(()));
+            let bob_balance = get_balance(accounts.bob);
+            transfer_in(200);
+            assert_eq!(
+                erc721.propose_swap(accounts.charlie, [1].to_vec(), [2].to_vec(), 0),
+                Ok(1)
+            );
+            // Alice pauses all operations.
+            set_caller(accounts.alice);
+            assert_eq!(erc721.pause(Operation::All), Ok(()));
+            // No swap can be proposed or accepted.
+            set_caller(accounts.bob);
+            set_value_transferred(0);
+            assert_eq!(
+                erc721.propose_swap(accounts.charlie, [1].to_vec(), [2].to_vec(), 0),
+                Err(Error::Paused)
+            );
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.accept_swap(1), Err(Error::Paused));
+            // Bob can still cancel the swap and get refunded.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.cancel_swap(1), Ok(()));
+            assert_eq!(erc721.swap(1), None);
+            assert_eq!(get_balance(accounts.bob), bob_balance);
+        }
+
+        #[ink::test]
+        fn set_user_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Create a new contract instance.
+            let mut erc721 = Erc721::new();
+            // Create token Id 1 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Bob cannot lease out Charlie's token.
             set_caller(accounts.bob);
-            // Bob cannot transfer not owned tokens.
-            assert_eq!(erc721.transfer(accounts.eve, 2), Err(Error::NotApproved));
+            assert_eq!(
+                erc721.set_user(1, Some(accounts.bob), 1_000),
+                Err(Error::NotApproved)
+            );
+            // Charlie leases token 1 to Bob until 1000.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.set_user(1, Some(accounts.bob), 1_000), Ok(()));
+            assert_eq!(erc721.user_of(1), Some(accounts.bob));
+            assert_eq!(erc721.user_expires(1), Some(1_000));
+            // The user role expires once the time has passed.
+            set_block_timestamp(1_001);
+            assert_eq!(erc721.user_of(1), None);
+            // Transferring the token clears its user.
+            assert_eq!(erc721.set_user(1, Some(accounts.bob), 2_000), Ok(()));
+            assert_eq!(erc721.transfer(accounts.eve, 1), Ok(()));
+            assert_eq!(erc721.user_of(1), None);
+            assert_eq!(erc721.user_expires(1), None);
         }
 
         #[ink::test]
-        fn approved_transfer_works() {
+        fn fractionalize_fails() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
             // Create a new contract instance.
             let mut erc721 = Erc721::new();
-            // Create token Id 1.
+            // Create token Id 1 for Charlie.
             let token_uri = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri), Ok(()));
-            // Token Id 1 is owned by Alice.
-            assert_eq!(erc721.owner_of(1), Some(accounts.alice));
-            // Approve token Id 1 transfer for Bob on behalf of Alice.
-            assert_eq!(erc721.approve(accounts.bob, 1), Ok(()));
-            // Set Bob as caller
-            set_caller(accounts.bob);
-            // Bob transfers token Id 1 from Alice to Eve.
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // No share contract code has been set.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.fractionalize(1, 1_000), Err(Error::NoShareCode));
+            // Only an admin can set the share contract code.
+            let code_hash = Hash::from([0x1; 32]);
             assert_eq!(
-                erc721.transfer_from(accounts.alice, accounts.eve, 1),
-                Ok(())
+                erc721.set_share_code_hash(Some(code_hash)),
+                Err(Error::MissingRole)
             );
-            // Token Id 1 is owned by Eve.
-            assert_eq!(erc721.owner_of(1), Some(accounts.eve));
-            // Alice does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.alice), 0);
-            // Bob does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.bob), 0);
-            // Eve owns 1 token.
-            assert_eq!(erc721.balance_of(accounts.eve), 1);
+            set_caller(accounts.alice);
+            assert_eq!(erc721.set_share_code_hash(Some(code_hash)), Ok(()));
+            assert_eq!(erc721.share_code_hash(), Some(code_hash));
+            // Only the owner can fractionalize a token.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.fractionalize(1, 1_000), Err(Error::NotOwner));
+            assert_eq!(erc721.fractionalize(2, 1_000), Err(Error::TokenNotFound));
+            // A token cannot be split into zero shares.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.fractionalize(1, 0), Err(Error::InvalidSupply));
+            // Token 1 is not vaulted and cannot be redeemed.
+            assert_eq!(erc721.shares_of(1), None);
+            assert_eq!(erc721.redeem(1), Err(Error::NotFractionalized));
         }
 
         #[ink::test]
-        fn approved_for_all_works() {
+        fn vault_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
             // Create a new contract instance.
             let mut erc721 = Erc721::new();
-            // Create token Id 1.
-            let token_uri1 = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri1), Ok(()));
-            // Create token Id 2.
-            let token_uri2 = String::from("https://example.com/nft/2");
-            assert_eq!(erc721.mint(2, token_uri2), Ok(()));
-            // Alice owns 2 tokens.
-            assert_eq!(erc721.balance_of(accounts.alice), 2);
-            // Approve token Id 1 transfer for Bob on behalf of Alice.
-            assert_eq!(erc721.set_approval_for_all(accounts.bob, true), Ok(()));
-            // Bob is an approved operator for Alice
-            assert!(erc721.is_approved_for_all(accounts.alice, accounts.bob));
-            // Set Bob as caller
-            set_caller(accounts.bob);
-            // Bob transfers token Id 1 from Alice to Eve.
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            // Create token Id 1 for Charlie.
+            let token_uri = String::from("https://example.com/nft/1");
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // The off-chain environment cannot instantiate or call the share
+            // contract, so the vault is driven past those calls here and through
+            // the public messages in the end-to-end tests. Charlie locks token 1
+            // behind 1000 shares of Frank's contract.
             assert_eq!(
-                erc721.transfer_from(accounts.alice, accounts.eve, 1),
+                erc721.lock_in_vault(accounts.charlie, 1, accounts.frank, 1_000),
                 Ok(())
             );
-            // Token Id 1 is owned by Eve.
-            assert_eq!(erc721.owner_of(1), Some(accounts.eve));
-            // Alice owns 1 token.
-            assert_eq!(erc721.balance_of(accounts.alice), 1);
-            // Bob transfers token Id 2 from Alice to Eve.
+            assert_eq!(erc721.owner_of(1), Some(contract));
+            assert_eq!(erc721.shares_of(1), Some(accounts.frank));
+            // The vaulted token can neither be moved nor burned.
+            set_caller(accounts.charlie);
+            assert_eq!(erc721.transfer(accounts.bob, 1), Err(Error::NotApproved));
+            set_caller(accounts.alice);
+            assert_eq!(erc721.burn(1), Err(Error::Escrowed));
+            // Bob, holding all shares, redeems token 1.
+            assert_eq!(erc721.release_from_vault(1, accounts.bob), Ok(()));
+            assert_eq!(erc721.owner_of(1), Some(accounts.bob));
+            assert_eq!(erc721.shares_of(1), None);
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let fractionalized =
+                <Fractionalized as ink::scale::Decode>::decode(&mut &events[8].data[..])
+                    .expect("encountered invalid event data");
+            assert_eq!(fractionalized.holder, accounts.charlie);
+            assert_eq!(fractionalized.supply, 1_000);
+            let redeemed = <Redeemed as ink::scale::Decode>::decode(
+                &mut &events[events.len() - 1].data[..],
+            )
+            .expect("encountered invalid event data");
+            assert_eq!(redeemed.holder, accounts.bob);
+        }
+
+        #[ink::test]
+        fn with_config_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Alice creates a collection administered by Bob.
+            let mut erc721 = Erc721::with_config(
+                String::from("Sirona"),
+                String::from("SIR"),
+                String::from("https://example.com/nft/"),
+                String::from("https://example.com/collection.json"),
+                accounts.bob,
+                Some(10),
+            );
+            assert_eq!(erc721.name(), "Sirona");
+            assert_eq!(erc721.symbol(), "SIR");
+            assert_eq!(erc721.contract_uri(), "https://example.com/collection.json");
+            assert!(erc721.has_role(Role::Admin, accounts.bob));
+            assert!(erc721.has_role(Role::Minter, accounts.bob));
+            assert!(!erc721.has_role(Role::Admin, accounts.alice));
+            // Each role granted to Bob emitted an event, the admin role first.
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(4, events.len());
+            let granted =
+                <RoleGranted as ink::scale::Decode>::decode(&mut &events[0].data[..])
+                    .expect("encountered invalid event data");
+            assert_eq!(granted.role, Role::Admin);
+            assert_eq!(granted.account, accounts.bob);
+            assert_eq!(granted.sender, accounts.alice);
+            assert_eq!(erc721.max_supply(), Some(10));
+            // Token Id 1 has no URI of its own and falls back to the base URI.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.mint(1, String::new()), Ok(()));
             assert_eq!(
-                erc721.transfer_from(accounts.alice, accounts.eve, 2),
-                Ok(())
+                erc721.token_uri(1),
+                Some(String::from("https://example.com/nft/1"))
             );
-            // Bob does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.bob), 0);
-            // Eve owns 2 tokens.
-            assert_eq!(erc721.balance_of(accounts.eve), 2);
-            // Remove operator approval for Bob on behalf of Alice.
-            set_caller(accounts.alice);
-            assert_eq!(erc721.set_approval_for_all(accounts.bob, false), Ok(()));
-            // Bob is not an approved operator for Alice.
-            assert!(!erc721.is_approved_for_all(accounts.alice, accounts.bob));
+            assert_eq!(erc721.token_uri(2), None);
         }
 
         #[ink::test]
-        fn approve_nonexistent_token_should_fail() {
+        fn set_base_and_contract_uri_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
             // Create a new contract instance.
             let mut erc721 = Erc721::new();
-            // Approve transfer of nonexistent token id 1
-            assert_eq!(erc721.approve(accounts.bob, 1), Err(Error::TokenNotFound));
+            // Only an admin can change the base and contract URIs.
+            set_caller(accounts.bob);
+            let base_uri = String::from("ipfs://base/");
+            assert_eq!(
+                erc721.set_base_uri(base_uri.clone()),
+                Err(Error::MissingRole)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc721.set_base_uri(base_uri.clone()), Ok(()));
+            assert_eq!(erc721.base_uri(), base_uri);
+            let contract_uri = String::from("ipfs://collection");
+            assert_eq!(erc721.set_contract_uri(contract_uri.clone()), Ok(()));
+            assert_eq!(erc721.contract_uri(), contract_uri);
+            // Both changes emitted an event, and all tokens may have a new URI.
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(events.len(), 7);
+            let event = <BatchMetadataUpdate as ink::scale::Decode>::decode(
+                &mut &events[5].data[..],
+            )
+            .expect("encountered invalid event");
+            assert_eq!((event.from_id, event.to_id), (0, TokenId::MAX));
+            let event = <ContractURIUpdated as ink::scale::Decode>::decode(
+                &mut &events[6].data[..],
+            )
+            .expect("encountered invalid event");
+            assert_eq!(event.contract_uri, contract_uri);
         }
 
         #[ink::test]
-        fn not_approved_transfer_should_fail() {
+        fn set_token_uri_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
             // Create a new contract instance.
             let mut erc721 = Erc721::new();
-            // Create token Id 1.
+            // Create token Id 1 for Charlie.
             let token_uri = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri), Ok(()));
-            // Alice owns 1 token.
-            assert_eq!(erc721.balance_of(accounts.alice), 1);
-            // Bob does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.bob), 0);
-            // Eve does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.eve), 0);
-            // Set Eve as caller
-            set_caller(accounts.eve);
-            // Eve is not an approved operator by Alice.
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Only a metadata manager can change the URI by default.
+            let new_uri = String::from("https://example.com/nft/1-fixed");
+            set_caller(accounts.charlie);
             assert_eq!(
-                erc721.transfer_from(accounts.alice, accounts.frank, 1),
-                Err(Error::NotApproved)
+                erc721.set_token_uri(1, new_uri.clone()),
+                Err(Error::NotAllowed)
             );
-            // Alice owns 1 token.
-            assert_eq!(erc721.balance_of(accounts.alice), 1);
-            // Bob does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.bob), 0);
-            // Eve does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.eve), 0);
+            set_caller(accounts.alice);
+            assert_eq!(erc721.set_token_uri(1, new_uri.clone()), Ok(()));
+            assert_eq!(erc721.token_uri(1), Some(new_uri));
+            // Alice lets owners change the URI as well.
+            assert_eq!(erc721.set_metadata_auth(MetadataAuth::Both), Ok(()));
+            set_caller(accounts.charlie);
+            let owner_uri = String::from("ipfs://charlie");
+            assert_eq!(erc721.set_token_uri(1, owner_uri.clone()), Ok(()));
+            assert_eq!(erc721.token_uri(1), Some(owner_uri.clone()));
+            // Bob is neither the owner nor a metadata manager.
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc721.set_token_uri(1, String::new()),
+                Err(Error::NotOwner)
+            );
+            // The change emitted a metadata update event.
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let event = <MetadataUpdate as ink::scale::Decode>::decode(
+                &mut &events.last().unwrap().data[..],
+            )
+            .expect("encountered invalid event");
+            assert_eq!(event.id, 1);
         }
 
         #[ink::test]
-        fn burn_works() {
+        fn freeze_metadata_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            // Create a new contract instance.
+            // Create a new contract instance with a base URI.
             let mut erc721 = Erc721::new();
-            // Create token Id 1 for Alice
-            let token_uri = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri), Ok(()));
-            // Alice owns 1 token.
-            assert_eq!(erc721.balance_of(accounts.alice), 1);
-            // Alice owns token Id 1.
-            assert_eq!(erc721.owner_of(1), Some(accounts.alice));
-            // Destroy token Id 1.
-            assert_eq!(erc721.burn(1), Ok(()));
-            // Alice does not own tokens.
-            assert_eq!(erc721.balance_of(accounts.alice), 0);
-            // Token Id 1 does not exist
-            assert_eq!(erc721.owner_of(1), None);
+            assert_eq!(erc721.set_base_uri(String::from("ipfs://base/")), Ok(()));
+            // Create token Ids 1 and 2 without a URI of their own.
+            for id in 1..=2 {
+                assert_eq!(erc721.mint(id, String::new()), Ok(()));
+            }
+            // Freezing token 1 keeps the URI it resolves to.
+            assert_eq!(erc721.freeze_token_metadata(1), Ok(()));
+            assert!(erc721.is_metadata_frozen(1));
+            assert_eq!(
+                erc721.set_token_uri(1, String::from("ipfs://other")),
+                Err(Error::MetadataFrozen)
+            );
+            assert_eq!(erc721.set_base_uri(String::from("ipfs://moved/")), Ok(()));
+            assert_eq!(erc721.token_uri(1), Some(String::from("ipfs://base/1")));
+            assert_eq!(erc721.token_uri(2), Some(String::from("ipfs://moved/2")));
+            // Only an admin can freeze the whole collection.
+            set_caller(accounts.bob);
+            assert_eq!(erc721.freeze_metadata(), Err(Error::MissingRole));
+            set_caller(accounts.alice);
+            assert_eq!(erc721.freeze_metadata(), Ok(()));
+            assert!(erc721.is_collection_metadata_frozen());
+            // Nothing can change any more.
+            assert_eq!(
+                erc721.set_token_uri(2, String::from("ipfs://other")),
+                Err(Error::MetadataFrozen)
+            );
+            assert_eq!(
+                erc721.set_base_uri(String::new()),
+                Err(Error::MetadataFrozen)
+            );
+            assert_eq!(erc721.freeze_metadata(), Err(Error::MetadataFrozen));
         }
 
         #[ink::test]
-        fn burn_fails_token_not_found() {
-            // Create a new contract instance.
+        fn freeze_token_metadata_requires_uri() {
+            // Create a new contract instance without a base URI.
             let mut erc721 = Erc721::new();
-            // Try burning a non-existent token
-            assert_eq!(erc721.burn(1), Err(Error::TokenNotFound));
+            // Create token Id 1 without a URI of its own.
+            assert_eq!(erc721.mint(1, String::new()), Ok(()));
+            assert_eq!(erc721.token_uri(1), None);
+            // Token 1 resolves to no URI and cannot be frozen.
+            assert_eq!(erc721.freeze_token_metadata(1), Err(Error::NoTokenURI));
+            assert!(!erc721.is_metadata_frozen(1));
+            assert_eq!(erc721.token_uri(1), None);
+            // Once it has a URI, it can.
+            let token_uri = String::from("ipfs://token/1");
+            assert_eq!(erc721.set_token_uri(1, token_uri.clone()), Ok(()));
+            assert_eq!(erc721.freeze_token_metadata(1), Ok(()));
+            assert_eq!(erc721.token_uri(1), Some(token_uri));
         }
 
         #[ink::test]
-        fn burn_fails_not_owner() {
+        fn psp34_works() {
+            use erc721_traits::PSP34;
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
             // Create a new contract instance.
             let mut erc721 = Erc721::new();
-            // Create token Id 1 for Alice
+            // Create token Id 1 for Charlie.
             let token_uri = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri), Ok(()));
-            // Try burning this token with a different account
-            set_caller(accounts.eve);
-            assert_eq!(erc721.burn(1), Err(Error::NotOwner));
+            assert_eq!(erc721.mint_to(accounts.charlie, 1, token_uri), Ok(()));
+            // Both interfaces see the same token.
+            assert_eq!(PSP34::owner_of(&erc721, Id::U32(1)), Some(accounts.charlie));
+            assert_eq!(PSP34::owner_of(&erc721, Id::U8(1)), Some(accounts.charlie));
+            assert_eq!(PSP34::owner_of(&erc721, Id::Bytes([1].to_vec())), None);
+            assert_eq!(PSP34::balance_of(&erc721, accounts.charlie), 1);
+            assert_eq!(PSP34::total_supply(&erc721), 1);
+            // Charlie approves Bob for token 1 through PSP34.
+            set_caller(accounts.charlie);
+            assert_eq!(
+                PSP34::approve(&mut erc721, accounts.bob, Some(Id::U32(1)), true),
+                Ok(())
+            );
+            assert_eq!(erc721.get_approved(1), Some(accounts.bob));
+            assert!(PSP34::allowance(
+                &erc721,
+                accounts.charlie,
+                accounts.bob,
+                Some(Id::U32(1))
+            ));
+            assert!(!PSP34::allowance(&erc721, accounts.charlie, accounts.bob, None));
+            // Token 1 is already approved.
+            assert_eq!(
+                PSP34::approve(&mut erc721, accounts.eve, Some(Id::U32(1)), true),
+                Err(PSP34Error::NotApproved)
+            );
+            // Charlie cannot approve themselves, while ERC-721 keeps its own error.
+            assert_eq!(
+                PSP34::approve(&mut erc721, accounts.charlie, None, true),
+                Err(PSP34Error::SelfApprove)
+            );
+            assert_eq!(
+                PSP34::approve(&mut erc721, accounts.charlie, Some(Id::U32(1)), true),
+                Err(PSP34Error::SelfApprove)
+            );
+            assert_eq!(
+                erc721.set_approval_for_all(accounts.charlie, true),
+                Err(Error::NotAllowed)
+            );
+            // Charlie disapproves Bob again.
+            assert_eq!(
+                PSP34::approve(&mut erc721, accounts.bob, Some(Id::U32(1)), false),
+                Ok(())
+            );
+            assert_eq!(erc721.get_approved(1), None);
+            // Both the approval and the disapproval emitted a PSP34 event.
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let approvals = events[events.len() - 2..]
+                .iter()
+                .map(|event| {
+                    <psp34::Approval as ink::scale::Decode>::decode(&mut &event.data[..])
+                        .expect("encountered invalid event data")
+                })
+                .map(|approval| (approval.operator, approval.id, approval.approved))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                approvals,
+                [
+                    (accounts.bob, Some(Id::U32(1)), true),
+                    (accounts.bob, Some(Id::U32(1)), false),
+                ]
+            );
+            // Bob cannot transfer a token they do not own.
+            set_caller(accounts.bob);
+            assert_eq!(
+                PSP34::transfer(&mut erc721, accounts.bob, Id::U32(1), Vec::new()),
+                Err(PSP34Error::NotApproved)
+            );
+            // Charlie transfers token 1 to Bob through PSP34.
+            set_caller(accounts.charlie);
+            assert_eq!(
+                PSP34::transfer(&mut erc721, accounts.bob, Id::U32(1), Vec::new()),
+                Ok(())
+            );
+            assert_eq!(Erc721Core::owner_of(&erc721, 1), Some(accounts.bob));
+            assert_eq!(
+                PSP34::transfer(&mut erc721, accounts.bob, Id::U32(2), Vec::new()),
+                Err(PSP34Error::TokenNotExists)
+            );
         }
 
         #[ink::test]
-        fn transfer_from_fails_not_owner() {
+        fn psp34_selectors_are_standard() {
+            use erc721_traits::PSP34;
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            // Create a new contract instance.
-            let mut erc721 = Erc721::new();
-            // Create token Id 1 for Alice
-            let token_uri1 = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri1), Ok(()));
-            // Bob can transfer Alice's tokens
-            assert_eq!(erc721.set_approval_for_all(accounts.bob, true), Ok(()));
-            // Set caller to Frank
-            set_caller(accounts.frank);
-            // Create token Id 2 for Frank
-            let token_uri2 = String::from("https://example.com/nft/2");
-            assert_eq!(erc721.mint(2, token_uri2), Ok(()));
-            // Set caller to Bob
-            set_caller(accounts.bob);
-            // Bob makes an invalid call to transfer_from (Alice is the token owner, not Frank)
+            let mut erc721: Erc721Ref =
+                ink::env::call::FromAccountId::from_account_id(accounts.alice);
+            // The trait name alone yields the standard PSP34 selectors.
+            let id = || Id::U32(1);
+            let builder = erc721.call();
             assert_eq!(
-                erc721.transfer_from(accounts.frank, accounts.bob, 1),
-                Err(Error::NotOwner)
+                selector_of(PSP34::collection_id(builder).params().exec_input()),
+                ink::selector_bytes!("PSP34::collection_id")
+            );
+            let balance_of = PSP34::balance_of(builder, accounts.bob);
+            assert_eq!(
+                selector_of(balance_of.params().exec_input()),
+                ink::selector_bytes!("PSP34::balance_of")
+            );
+            assert_eq!(
+                selector_of(PSP34::owner_of(builder, id()).params().exec_input()),
+                ink::selector_bytes!("PSP34::owner_of")
+            );
+            let allowance = PSP34::allowance(builder, accounts.bob, accounts.eve, None);
+            assert_eq!(
+                selector_of(allowance.params().exec_input()),
+                ink::selector_bytes!("PSP34::allowance")
+            );
+            assert_eq!(
+                selector_of(PSP34::total_supply(builder).params().exec_input()),
+                ink::selector_bytes!("PSP34::total_supply")
+            );
+            let builder = erc721.call_mut();
+            let approve = PSP34::approve(builder, accounts.bob, Some(id()), true);
+            assert_eq!(
+                selector_of(approve.params().exec_input()),
+                ink::selector_bytes!("PSP34::approve")
+            );
+            let transfer = PSP34::transfer(builder, accounts.bob, id(), Vec::new());
+            assert_eq!(
+                selector_of(transfer.params().exec_input()),
+                ink::selector_bytes!("PSP34::transfer")
             );
         }
 
         #[ink::test]
-        fn transfer_fails_not_owner() {
+        fn erc721_selectors_are_unchanged() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            // Create a new contract instance.
-            let mut erc721 = Erc721::new();
-            // Create token Id 1 for Alice
-            let token_uri = String::from("https://example.com/nft/1");
-            assert_eq!(erc721.mint(1, token_uri), Ok(()));
-            // Bob can transfer Alice's tokens
-            assert_eq!(erc721.set_approval_for_all(accounts.bob, true), Ok(()));
-            // Set caller to Bob
-            set_caller(accounts.bob);
-            // Bob makes an invalid call to transfer (he is not the token owner, Alice is)
-            assert_eq!(erc721.transfer(accounts.bob, 1), Err(Error::NotOwner));
+            let mut erc721: Erc721Ref =
+                ink::env::call::FromAccountId::from_account_id(accounts.alice);
+            // The messages moved into traits keep the selectors of their names.
+            let builder = erc721.call();
+            let balance_of = Erc721Core::balance_of(builder, accounts.bob);
+            assert_eq!(
+                selector_of(balance_of.params().exec_input()),
+                ink::selector_bytes!("balance_of")
+            );
+            assert_eq!(
+                selector_of(Erc721Core::owner_of(builder, 1).params().exec_input()),
+                ink::selector_bytes!("owner_of")
+            );
+            assert_eq!(
+                selector_of(builder.get_approved(1).params().exec_input()),
+                ink::selector_bytes!("get_approved")
+            );
+            let approved = builder.is_approved_for_all(accounts.bob, accounts.eve);
+            assert_eq!(
+                selector_of(approved.params().exec_input()),
+                ink::selector_bytes!("is_approved_for_all")
+            );
+            assert_eq!(
+                selector_of(builder.token_uri(1).params().exec_input()),
+                ink::selector_bytes!("token_uri")
+            );
+            let builder = erc721.call_mut();
+            let transfer = Erc721Core::transfer(builder, accounts.bob, 1);
+            assert_eq!(
+                selector_of(transfer.params().exec_input()),
+                ink::selector_bytes!("transfer")
+            );
+            let transfer_from = builder.transfer_from(accounts.bob, accounts.eve, 1);
+            assert_eq!(
+                selector_of(transfer_from.params().exec_input()),
+                ink::selector_bytes!("transfer_from")
+            );
+            let safe_transfer_from =
+                builder.safe_transfer_from(accounts.bob, accounts.eve, 1, Vec::new());
+            assert_eq!(
+                selector_of(safe_transfer_from.params().exec_input()),
+                ink::selector_bytes!("safe_transfer_from")
+            );
+            let approval_for_all = builder.set_approval_for_all(accounts.bob, true);
+            assert_eq!(
+                selector_of(approval_for_all.params().exec_input()),
+                ink::selector_bytes!("set_approval_for_all")
+            );
+            let approve = Erc721Approvals::approve(builder, accounts.bob, 1);
+            assert_eq!(
+                selector_of(approve.params().exec_input()),
+                ink::selector_bytes!("approve")
+            );
         }
 
         fn set_caller(sender: AccountId) {
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(sender);
         }
+
+        fn selector_of<Args: Encode>(
+            input: &ink::env::call::ExecutionInput<Args>,
+        ) -> [u8; 4] {
+            let mut selector = [0; 4];
+            selector.copy_from_slice(&input.encode()[..4]);
+            selector
+        }
+
+        fn set_value_transferred(value: Balance) {
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(value);
+        }
+
+        fn transfer_in(value: Balance) {
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(value);
+        }
+
+        fn set_balance(account: AccountId, balance: Balance) {
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                account, balance,
+            );
+        }
+
+        fn get_balance(account: AccountId) -> Balance {
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account)
+                .expect("cannot get account balance")
+        }
+
+        fn set_block_timestamp(timestamp: Timestamp) {
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(
+                timestamp,
+            );
+        }
+
+        fn ecdsa_secret_key() -> secp256k1::SecretKey {
+            secp256k1::SecretKey::from_slice(&[0x7; 32])
+                .expect("secret key has the right length")
+        }
+
+        fn eth_address(secret_key: &secp256k1::SecretKey) -> EthAddress {
+            let public_key = secret_key.public_key(secp256k1::SECP256K1).serialize();
+            let mut address = [0x0; 20];
+            ink::env::ecdsa_to_eth_address(&public_key, &mut address)
+                .expect("public key is valid");
+            address
+        }
+
+        fn ecdsa_sign(
+            erc721: &Erc721,
+            secret_key: &secp256k1::SecretKey,
+            payload: &[u8],
+        ) -> [u8; 65] {
+            let message =
+                secp256k1::Message::from_digest(erc721.eth_message_hash(payload));
+            let (recovery_id, compact) = secp256k1::SECP256K1
+                .sign_ecdsa_recoverable(&message, secret_key)
+                .serialize_compact();
+            let mut signature = [0x0; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
+
+        fn sr25519_keypair() -> schnorrkel::Keypair {
+            schnorrkel::MiniSecretKey::from_bytes(&[0x7; 32])
+                .expect("secret key has the right length")
+                .expand_to_keypair(schnorrkel::ExpansionMode::Ed25519)
+        }
+
+        fn sr25519_sign(keypair: &schnorrkel::Keypair, message: &[u8]) -> [u8; 64] {
+            keypair
+                .sign(schnorrkel::signing_context(b"substrate").bytes(message))
+                .to_bytes()
+        }
+    }
+
+    /// End-to-end tests, run against a contracts node with
+    /// `cargo test --features e2e-tests`.
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+        use ink_e2e::ContractsBackend;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn fractionalize_and_redeem_works<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            // Alice creates a collection and registers the share contract code.
+            let mut constructor = Erc721Ref::new();
+            let contract = client
+                .instantiate("erc721", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut erc721 = contract.call_builder::<Erc721>();
+            let code_hash = client
+                .upload("shares", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let set_code_hash = erc721.set_share_code_hash(Some(code_hash));
+            client
+                .call(&ink_e2e::alice(), &set_code_hash)
+                .submit()
+                .await
+                .expect("set_share_code_hash failed");
+            // Alice creates token Id 1 and splits it into 1000 shares.
+            let mint = erc721.mint(1, String::from("https://example.com/nft/1"));
+            client
+                .call(&ink_e2e::alice(), &mint)
+                .submit()
+                .await
+                .expect("mint failed");
+            let fractionalize = erc721.fractionalize(1, 1_000);
+            let shares = client
+                .call(&ink_e2e::alice(), &fractionalize)
+                .submit()
+                .await
+                .expect("fractionalize failed")
+                .return_value()
+                .expect("fractionalize returned an error");
+            let owner_of = Erc721Core::owner_of(&erc721, 1);
+            let owner = client.call(&ink_e2e::bob(), &owner_of).dry_run().await?;
+            assert_eq!(owner.return_value(), Some(contract.account_id));
+            let shares_of = erc721.shares_of(1);
+            let vault = client.call(&ink_e2e::bob(), &shares_of).dry_run().await?;
+            assert_eq!(vault.return_value(), Some(shares));
+            // Alice gives 400 shares to Bob and can no longer redeem token 1.
+            let mut share_ref: SharesRef =
+                ink::env::call::FromAccountId::from_account_id(shares);
+            let give = PSP22::transfer(share_ref.call_mut(), bob, 400, Vec::new());
+            client
+                .call(&ink_e2e::alice(), &give)
+                .submit()
+                .await
+                .expect("share transfer failed");
+            let redeem = erc721.redeem(1);
+            let result = client.call(&ink_e2e::alice(), &redeem).dry_run().await?;
+            assert_eq!(result.return_value(), Err(Error::InsufficientShares));
+            // Bob hands the shares back and Alice redeems token 1, burning them all.
+            let give_back = PSP22::transfer(share_ref.call_mut(), alice, 400, Vec::new());
+            client
+                .call(&ink_e2e::bob(), &give_back)
+                .submit()
+                .await
+                .expect("share transfer failed");
+            client
+                .call(&ink_e2e::alice(), &redeem)
+                .submit()
+                .await
+                .expect("redeem failed");
+            let owner = client.call(&ink_e2e::bob(), &owner_of).dry_run().await?;
+            assert_eq!(owner.return_value(), Some(alice));
+            let vault = client.call(&ink_e2e::bob(), &shares_of).dry_run().await?;
+            assert_eq!(vault.return_value(), None);
+            let total_supply = PSP22::total_supply(share_ref.call());
+            let supply = client.call(&ink_e2e::bob(), &total_supply).dry_run().await?;
+            assert_eq!(supply.return_value(), 0);
+            Ok(())
+        }
     }
 }
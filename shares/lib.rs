@@ -0,0 +1,365 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+use ink::prelude::string::String;
+use ink::prelude::vec::Vec;
+use ink::primitives::AccountId;
+
+pub use self::shares::{Shares, SharesRef};
+
+/// The PSP22 error type.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum PSP22Error {
+    /// A custom error.
+    Custom(String),
+    /// The balance of the sender is too low.
+    InsufficientBalance,
+    /// The allowance of the caller is too low.
+    InsufficientAllowance,
+    /// The recipient is the zero address.
+    ZeroRecipientAddress,
+    /// The sender is the zero address.
+    ZeroSenderAddress,
+    /// The recipient rejected the transfer.
+    SafeTransferCheckFailed(String),
+}
+
+/// The PSP22 fungible token interface.
+#[ink::trait_definition]
+pub trait PSP22 {
+    /// Returns the total token supply.
+    #[ink(message)]
+    fn total_supply(&self) -> u128;
+
+    /// Returns the balance of the owner.
+    #[ink(message)]
+    fn balance_of(&self, owner: AccountId) -> u128;
+
+    /// Returns the amount `spender` is allowed to withdraw from `owner`.
+    #[ink(message)]
+    fn allowance(&self, owner: AccountId, spender: AccountId) -> u128;
+
+    /// Transfers `value` from the caller to `to`.
+    #[ink(message)]
+    fn transfer(
+        &mut self,
+        to: AccountId,
+        value: u128,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22Error>;
+
+    /// Transfers `value` from `from` to `to` using the allowance of the caller.
+    #[ink(message)]
+    fn transfer_from(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        value: u128,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22Error>;
+
+    /// Allows `spender` to withdraw up to `value` from the caller.
+    #[ink(message)]
+    fn approve(&mut self, spender: AccountId, value: u128) -> Result<(), PSP22Error>;
+
+    /// Increases the allowance of `spender` by `delta_value`.
+    #[ink(message)]
+    fn increase_allowance(
+        &mut self,
+        spender: AccountId,
+        delta_value: u128,
+    ) -> Result<(), PSP22Error>;
+
+    /// Decreases the allowance of `spender` by `delta_value`.
+    #[ink(message)]
+    fn decrease_allowance(
+        &mut self,
+        spender: AccountId,
+        delta_value: u128,
+    ) -> Result<(), PSP22Error>;
+}
+
+/// Fungible shares of a token locked in an `Erc721` vault.
+///
+/// The vault instantiating the contract is the only account allowed to burn
+/// shares, which it does when the token is redeemed.
+#[ink::contract]
+mod shares {
+    use crate::PSP22Error;
+    use ink::prelude::string::String;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    #[ink(storage)]
+    pub struct Shares {
+        /// The vault that instantiated the contract.
+        vault: AccountId,
+        /// The total supply of shares.
+        total_supply: Balance,
+        /// Mapping from owner to number of owned shares.
+        balances: Mapping<AccountId, Balance>,
+        /// Mapping from owner and spender to the allowance.
+        allowances: Mapping<(AccountId, AccountId), Balance>,
+    }
+
+    /// Event emitted when shares are transferred, minted or burned.
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    /// Event emitted when an allowance changes.
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        amount: Balance,
+    }
+
+    impl Shares {
+        /// Creates the shares of a vaulted token, minting `supply` to `holder`.
+        ///
+        /// The caller becomes the vault.
+        #[ink(constructor)]
+        pub fn new(holder: AccountId, supply: Balance) -> Self {
+            let mut balances = Mapping::new();
+            balances.insert(holder, &supply);
+            Self::env().emit_event(Transfer {
+                from: None,
+                to: Some(holder),
+                value: supply,
+            });
+            Self {
+                vault: Self::env().caller(),
+                total_supply: supply,
+                balances,
+                allowances: Mapping::new(),
+            }
+        }
+
+        /// Returns the vault holding the token the shares are backed by.
+        #[ink(message)]
+        pub fn vault(&self) -> AccountId {
+            self.vault
+        }
+
+        /// Burns `value` shares of `from`. Only the vault can burn shares.
+        #[ink(message)]
+        pub fn burn(
+            &mut self,
+            from: AccountId,
+            value: Balance,
+        ) -> Result<(), PSP22Error> {
+            if self.env().caller() != self.vault {
+                return Err(PSP22Error::Custom(String::from("NotVault")));
+            }
+            let balance = self
+                .balance_of_or_zero(from)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.balances.insert(from, &balance);
+            self.total_supply -= value;
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: None,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Moves `value` shares from `from` to `to`.
+        fn transfer_shares(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<(), PSP22Error> {
+            let from_balance = self
+                .balance_of_or_zero(from)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientBalance)?;
+            self.balances.insert(from, &from_balance);
+            let to_balance = self.balance_of_or_zero(to);
+            self.balances.insert(to, &(to_balance + value));
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+            Ok(())
+        }
+
+        /// Sets the allowance of `spender` over the shares of `owner`.
+        fn set_allowance(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            amount: Balance,
+        ) {
+            self.allowances.insert((owner, spender), &amount);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                amount,
+            });
+        }
+
+        /// Returns the number of shares of an account.
+        fn balance_of_or_zero(&self, owner: AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or(0)
+        }
+    }
+
+    impl crate::PSP22 for Shares {
+        #[ink(message)]
+        fn total_supply(&self) -> Balance {
+            self.total_supply
+        }
+
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balance_of_or_zero(owner)
+        }
+
+        #[ink(message)]
+        fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowances.get((owner, spender)).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            self.transfer_shares(from, to, value)
+        }
+
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            let allowance = crate::PSP22::allowance(self, from, caller)
+                .checked_sub(value)
+                .ok_or(PSP22Error::InsufficientAllowance)?;
+            self.transfer_shares(from, to, value)?;
+            self.set_allowance(from, caller, allowance);
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.set_allowance(owner, spender, value);
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn increase_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            let allowance =
+                crate::PSP22::allowance(self, owner, spender).saturating_add(delta_value);
+            self.set_allowance(owner, spender, allowance);
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn decrease_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            let allowance = crate::PSP22::allowance(self, owner, spender)
+                .checked_sub(delta_value)
+                .ok_or(PSP22Error::InsufficientAllowance)?;
+            self.set_allowance(owner, spender, allowance);
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::PSP22;
+
+        #[ink::test]
+        fn new_mints_supply_to_holder() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Alice, the vault, creates 1000 shares for Bob.
+            let shares = Shares::new(accounts.bob, 1_000);
+            assert_eq!(shares.vault(), accounts.alice);
+            assert_eq!(shares.total_supply(), 1_000);
+            assert_eq!(shares.balance_of(accounts.bob), 1_000);
+        }
+
+        #[ink::test]
+        fn transfer_from_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut shares = Shares::new(accounts.bob, 1_000);
+            // Bob allows Charlie to spend 300 shares.
+            set_caller(accounts.bob);
+            assert_eq!(shares.approve(accounts.charlie, 300), Ok(()));
+            // Charlie cannot spend more than allowed.
+            set_caller(accounts.charlie);
+            assert_eq!(
+                shares.transfer_from(accounts.bob, accounts.eve, 301, Vec::new()),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                shares.transfer_from(accounts.bob, accounts.eve, 300, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(shares.balance_of(accounts.eve), 300);
+            assert_eq!(shares.allowance(accounts.bob, accounts.charlie), 0);
+        }
+
+        #[ink::test]
+        fn only_vault_can_burn() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut shares = Shares::new(accounts.bob, 1_000);
+            // Bob cannot burn their own shares.
+            set_caller(accounts.bob);
+            assert_eq!(
+                shares.burn(accounts.bob, 1_000),
+                Err(PSP22Error::Custom(String::from("NotVault")))
+            );
+            // The vault burns all of Bob's shares.
+            set_caller(accounts.alice);
+            assert_eq!(
+                shares.burn(accounts.bob, 1_001),
+                Err(PSP22Error::InsufficientBalance)
+            );
+            assert_eq!(shares.burn(accounts.bob, 1_000), Ok(()));
+            assert_eq!(shares.total_supply(), 0);
+        }
+
+        fn set_caller(sender: AccountId) {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(sender);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Interfaces of the `erc721` contract.
+//!
+//! Other contracts can depend on this crate and call a collection through typed
+//! refs, e.g. `ink::contract_ref!(Erc721Core)`, instead of building selectors.
+
+use ink::prelude::string::String;
+use ink::prelude::vec::Vec;
+use ink::primitives::AccountId;
+
+/// A token ID.
+pub type TokenId = u32;
+/// The URI, where the asset is stored.
+pub type TokenURI = String;
+
+/// The error type of the `erc721` contract.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum Error {
+    NotOwner,
+    NotApproved,
+    TokenExists,
+    TokenNotFound,
+    CannotInsert,
+    CannotFetchValue,
+    NotAllowed,
+    MissingRole,
+    QueryTooLarge,
+    InvalidRoyalty,
+    SafeTransferCheckFailed,
+    Paused,
+    UpgradeFailed,
+    InvalidStorageVersion,
+    Soulbound,
+    SupplyExceeded,
+    MintLimitReached,
+    MintClosed,
+    InvalidSignature,
+    SignatureExpired,
+    NonceAlreadyUsed,
+    InvalidNonce,
+    InsufficientDeposit,
+    TransferFailed,
+    NotListed,
+    IncorrectPayment,
+    FeesExceedPrice,
+    InvalidAuction,
+    NoAuction,
+    AuctionEnded,
+    AuctionNotEnded,
+    BidTooLow,
+    NoOffer,
+    OfferExpired,
+    NoSwap,
+    NoShareCode,
+    SharesCallFailed,
+    NotFractionalized,
+    InsufficientShares,
+    MetadataFrozen,
+    Escrowed,
+    InvalidSupply,
+    NoTokenURI,
+}
+
+/// The core ERC-721 interface: balances, ownership and transfers.
+///
+/// The messages of the ERC-721 interfaces that `nfts` exposed before they were
+/// moved into traits keep their original selectors, so existing callers are not
+/// broken.
+#[ink::trait_definition]
+pub trait Erc721Core {
+    /// Returns the balance of the owner.
+    ///
+    /// This represents the amount of unique tokens the owner has.
+    #[ink(message, selector = 0x0f755a56)]
+    fn balance_of(&self, owner: AccountId) -> u32;
+
+    /// Returns the owner of the token.
+    #[ink(message, selector = 0x99720c1e)]
+    fn owner_of(&self, id: TokenId) -> Option<AccountId>;
+
+    /// Transfers the token from the caller to the given destination.
+    #[ink(message, selector = 0x84a15da1)]
+    fn transfer(&mut self, destination: AccountId, id: TokenId) -> Result<(), Error>;
+
+    /// Transfer approved or owned token.
+    #[ink(message, selector = 0x0b396f18)]
+    fn transfer_from(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        id: TokenId,
+    ) -> Result<(), Error>;
+
+    /// Transfer approved or owned token, checking that a contract destination
+    /// accepts it.
+    ///
+    /// If `to` is a contract, its `Erc721Receiver::on_erc721_received` message is
+    /// called with `data` and the transfer is reverted unless it returns `true`.
+    #[ink(message, selector = 0x8c474972)]
+    fn safe_transfer_from(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        id: TokenId,
+        data: Vec<u8>,
+    ) -> Result<(), Error>;
+}
+
+/// The ERC-721 approvals interface.
+#[ink::trait_definition]
+pub trait Erc721Approvals {
+    /// Returns the approved account ID for this token if any.
+    #[ink(message, selector = 0x27592dea)]
+    fn get_approved(&self, id: TokenId) -> Option<AccountId>;
+
+    /// Returns `true` if the operator is approved by the owner.
+    #[ink(message, selector = 0x0f5922e9)]
+    fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool;
+
+    /// Approves or disapproves the operator for all tokens of the caller.
+    #[ink(message, selector = 0xcfd0c27b)]
+    fn set_approval_for_all(
+        &mut self,
+        to: AccountId,
+        approved: bool,
+    ) -> Result<(), Error>;
+
+    /// Approves the account to transfer the specified token on behalf of the caller.
+    #[ink(message, selector = 0x681266a0)]
+    fn approve(&mut self, to: AccountId, id: TokenId) -> Result<(), Error>;
+}
+
+/// The ERC-721 metadata interface.
+#[ink::trait_definition]
+pub trait Erc721Metadata {
+    /// Returns the name of the collection.
+    #[ink(message)]
+    fn name(&self) -> String;
+
+    /// Returns the symbol of the collection.
+    #[ink(message)]
+    fn symbol(&self) -> String;
+
+    /// Fetches the URI for a given token ID.
+    ///
+    /// Tokens without a URI of their own resolve to the base URI followed by
+    /// their ID, if a base URI is set.
+    #[ink(message, selector = 0x5b64e66a)]
+    fn token_uri(&self, id: TokenId) -> Option<TokenURI>;
+}
+
+/// A PSP34 token ID.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum Id {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Bytes(Vec<u8>),
+}
+
+/// The PSP34 error type.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum PSP34Error {
+    /// A custom error.
+    Custom(String),
+    /// The owner approved themselves.
+    SelfApprove,
+    /// The caller is not approved for the token.
+    NotApproved,
+    /// The token already exists.
+    TokenExists,
+    /// The token does not exist.
+    TokenNotExists,
+    /// The recipient rejected the transfer.
+    SafeTransferCheckFailed(String),
+}
+
+impl From<Error> for PSP34Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::NotApproved | Error::NotOwner | Error::CannotInsert => {
+                PSP34Error::NotApproved
+            }
+            Error::TokenExists => PSP34Error::TokenExists,
+            Error::TokenNotFound => PSP34Error::TokenNotExists,
+            Error::SafeTransferCheckFailed => {
+                PSP34Error::SafeTransferCheckFailed(String::new())
+            }
+            error => PSP34Error::Custom(ink::prelude::format!("{error:?}")),
+        }
+    }
+}
+
+/// The PSP34 non-fungible token interface.
+#[ink::trait_definition]
+pub trait PSP34 {
+    /// Returns the ID of the collection.
+    #[ink(message)]
+    fn collection_id(&self) -> Id;
+
+    /// Returns the number of tokens owned by `owner`.
+    #[ink(message)]
+    fn balance_of(&self, owner: AccountId) -> u32;
+
+    /// Returns the owner of the token, if it exists.
+    #[ink(message)]
+    fn owner_of(&self, id: Id) -> Option<AccountId>;
+
+    /// Returns `true` if `operator` may transfer the token `id` of `owner`, or
+    /// all of their tokens if `id` is `None`.
+    #[ink(message)]
+    fn allowance(&self, owner: AccountId, operator: AccountId, id: Option<Id>) -> bool;
+
+    /// Approves or disapproves `operator` for the token `id` of the caller, or
+    /// for all of their tokens if `id` is `None`.
+    #[ink(message)]
+    fn approve(
+        &mut self,
+        operator: AccountId,
+        id: Option<Id>,
+        approved: bool,
+    ) -> Result<(), PSP34Error>;
+
+    /// Transfers the token `id` of the caller to `to`.
+    #[ink(message)]
+    fn transfer(
+        &mut self,
+        to: AccountId,
+        id: Id,
+        data: Vec<u8>,
+    ) -> Result<(), PSP34Error>;
+
+    /// Returns the number of existing tokens.
+    #[ink(message)]
+    fn total_supply(&self) -> u128;
+}
+
+/// The events of the PSP34 standard.
+///
+/// They keep the standard names and field types, which make up their signature
+/// topics, so PSP34 wallets and indexers recognize them.
+pub mod psp34 {
+    use super::Id;
+    use ink::primitives::AccountId;
+
+    /// Event emitted when a token is minted, transferred or burned.
+    #[ink::event]
+    pub struct Transfer {
+        #[ink(topic)]
+        pub from: Option<AccountId>,
+        #[ink(topic)]
+        pub to: Option<AccountId>,
+        pub id: Id,
+    }
+
+    /// Event emitted when an operator is approved or disapproved for a token,
+    /// or for all tokens of the owner if `id` is `None`.
+    #[ink::event]
+    pub struct Approval {
+        #[ink(topic)]
+        pub owner: AccountId,
+        #[ink(topic)]
+        pub operator: AccountId,
+        #[ink(topic)]
+        pub id: Option<Id>,
+        pub approved: bool,
+    }
+}
+
+/// Interface of contracts accepting tokens through `safe_transfer_from`.
+#[ink::trait_definition]
+pub trait Erc721Receiver {
+    /// Handles the receipt of token `id`, sent by `operator` on behalf of `from`.
+    ///
+    /// Returns `true` to accept the token. Any other outcome reverts the transfer.
+    #[ink(message)]
+    fn on_erc721_received(
+        &mut self,
+        operator: AccountId,
+        from: AccountId,
+        id: TokenId,
+        data: Vec<u8>,
+    ) -> bool;
+}
+